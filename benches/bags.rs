@@ -0,0 +1,54 @@
+//! Builds and queries the bag graph for a synthetic set of rules.
+//!
+//! Run with `cargo bench --bench bags`.
+
+mod common;
+
+use advent_of_code_2020::bags::{validate, BagGraph, BagSpec, Rule, Total};
+use advent_of_code_2020::random::Lcg;
+use common::time;
+
+use std::collections::BTreeMap;
+
+const COLOURS: usize = 10_000;
+const ROUNDS: u32 = 20;
+
+/// Rules where every colour contains up to three colours with a higher
+/// number, so the nesting is deep but never cyclic
+fn generate_rules(colours: usize) -> Vec<Rule> {
+    let name = |colour: usize| format!("colour {}", colour);
+    let mut rng = Lcg::new(2020);
+    let mut random = |bound: usize| rng.below(bound as u64) as usize;
+    (0..colours)
+        .map(|colour| {
+            let remaining = colours - colour - 1;
+            let specs = (0..random(4).min(remaining))
+                .map(|_| BagSpec {
+                    number: 1 + random(5),
+                    bagtype: name(colour + 1 + random(remaining.min(50))),
+                })
+                .collect();
+            (name(colour), specs)
+        })
+        .collect()
+}
+
+fn main() {
+    let rules = generate_rules(COLOURS);
+    let (validating, errors) = time(ROUNDS, || validate(&rules));
+    assert!(errors.is_empty());
+    let map: BTreeMap<String, Vec<BagSpec>> = rules.into_iter().collect();
+    let (building, graph) = time(ROUNDS, || BagGraph::new(&map));
+    let middle = format!("colour {}", COLOURS / 2);
+    let (ancestors, found) = time(ROUNDS, || graph.ancestors(&middle).unwrap().len());
+
+    let overflowing = graph
+        .totals()
+        .filter(|&(_, total)| total == Total::Overflow)
+        .count();
+    println!("{} colours, {} can contain '{}'", COLOURS, found, middle);
+    println!("{} colours hold too many bags to count", overflowing);
+    println!("validating: {:>10.2?}", validating);
+    println!("building:   {:>10.2?}", building);
+    println!("ancestors:  {:>10.2?}", ancestors);
+}
@@ -0,0 +1,12 @@
+use std::time::{Duration, Instant};
+
+/// The average time `f` takes over `rounds` calls, after one call to warm up,
+/// and the result of the last call
+pub fn time<T>(rounds: u32, mut f: impl FnMut() -> T) -> (Duration, T) {
+    let mut result = f();
+    let start = Instant::now();
+    for _ in 0..rounds {
+        result = f();
+    }
+    (start.elapsed() / rounds, result)
+}
@@ -0,0 +1,50 @@
+//! Compares the interpreter with the compiled backend on a long boot program.
+//!
+//! Run with `cargo bench --bench console`.
+
+mod common;
+
+use advent_of_code_2020::console::compiled::CompiledProgram;
+use advent_of_code_2020::console::{BootInstruction, Machine};
+use advent_of_code_2020::random::Lcg;
+use common::time;
+
+const PROGRAM_LENGTH: usize = 1_000_000;
+const ROUNDS: u32 = 20;
+
+/// A program that visits most of its instructions once and then halts: it
+/// jumps forward over short stretches and back into the ones it skipped.
+fn generate_program(len: usize) -> Vec<BootInstruction> {
+    let mut program = Vec::with_capacity(len);
+    let mut rng = Lcg::new(2020);
+    while program.len() + 4 <= len {
+        let value = rng.below(16) as isize - 8;
+        // jump over the block of two, run it, and jump past the end again
+        program.push(BootInstruction::Jmp(3));
+        program.push(BootInstruction::Acc(value));
+        program.push(BootInstruction::Jmp(3));
+        program.push(BootInstruction::Jmp(-2));
+    }
+    while program.len() < len {
+        program.push(BootInstruction::Nop(0));
+    }
+    program
+}
+
+fn main() {
+    let program = generate_program(PROGRAM_LENGTH);
+
+    let (interpreted, expected) = time(ROUNDS, || {
+        let mut machine = Machine::new(&program);
+        let outcome = machine.run();
+        (outcome, machine.accumulator())
+    });
+    let (compiling, compiled) = time(ROUNDS, || CompiledProgram::new(&program));
+    let (running, result) = time(ROUNDS, || compiled.run());
+    assert_eq!(result, expected);
+
+    println!("{} instructions, outcome {:?}", PROGRAM_LENGTH, expected);
+    println!("interpreter: {:>10.2?} per run", interpreted);
+    println!("compiling:   {:>10.2?}", compiling);
+    println!("compiled:    {:>10.2?} per run", running);
+}
@@ -0,0 +1,720 @@
+use serde::Serialize;
+
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{self, Write};
+
+/// One entry on the right hand side of a rule: `number` bags of `bagtype`
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BagSpec {
+    pub number: usize,
+    pub bagtype: String,
+}
+
+/// A rule as it appears in the input: a bag and what it must contain
+pub type Rule = (String, Vec<BagSpec>);
+
+/// A problem with a set of rules that makes the questions about it
+/// meaningless
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RuleError {
+    /// more than one rule for the same bag, with the 1-based numbers of the
+    /// rules
+    Duplicate { bag: String, rules: Vec<usize> },
+    /// a rule mentions a bag that has no rule itself
+    Dangling { bag: String, missing: String },
+    /// bags that eventually contain themselves; the path ends at the bag it
+    /// starts with
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for RuleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RuleError::Duplicate { bag, rules } => {
+                let rules: Vec<String> = rules.iter().map(|rule| rule.to_string()).collect();
+                write!(
+                    f,
+                    "'{}' bags have more than one rule: rules {}",
+                    bag,
+                    rules.join(", ")
+                )
+            }
+            RuleError::Dangling { bag, missing } => {
+                write!(
+                    f,
+                    "'{}' bags contain '{}' bags, which have no rule",
+                    bag, missing
+                )
+            }
+            RuleError::Cycle(path) => write!(f, "bags contain themselves: {}", path.join(" -> ")),
+        }
+    }
+}
+
+/// Check rules before answering questions about them. Reports every
+/// duplicate rule, every reference to a bag without a rule, and the cycles
+/// in the rules as they are used when collected in a map (where the last
+/// rule for a bag wins).
+pub fn validate(rules: &[Rule]) -> Vec<RuleError> {
+    let mut errors = Vec::new();
+    let mut numbers: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+    for (number, (bag, _)) in rules.iter().enumerate() {
+        numbers.entry(bag).or_default().push(number + 1);
+    }
+    for (bag, rules) in &numbers {
+        if rules.len() > 1 {
+            errors.push(RuleError::Duplicate {
+                bag: bag.to_string(),
+                rules: rules.clone(),
+            });
+        }
+    }
+
+    for (bag, specs) in rules {
+        for spec in specs {
+            if !numbers.contains_key(spec.bagtype.as_str()) {
+                errors.push(RuleError::Dangling {
+                    bag: bag.clone(),
+                    missing: spec.bagtype.clone(),
+                });
+            }
+        }
+    }
+
+    let map: BTreeMap<String, Vec<BagSpec>> = rules.iter().cloned().collect();
+    for cycle in BagGraph::new(&map).cycles() {
+        errors.push(RuleError::Cycle(
+            cycle.into_iter().map(String::from).collect(),
+        ));
+    }
+    errors
+}
+
+/// The total number of bags inside a bag
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Total {
+    Bags(usize),
+    /// more than fits in a `usize`
+    Overflow,
+    /// the bag contains itself somewhere, so there is no end to it
+    Cyclic,
+}
+
+impl fmt::Display for Total {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Total::Bags(number) => write!(f, "{}", number),
+            Total::Overflow => write!(f, "more than {}", usize::MAX),
+            Total::Cyclic => write!(f, "no end to it"),
+        }
+    }
+}
+
+/// A rule as an edge of the graph: bags of colour `from` contain `number`
+/// bags of colour `to`
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Edge<'a> {
+    pub from: &'a str,
+    pub to: &'a str,
+    pub number: usize,
+}
+
+/// (Part of) the graph as plain nodes and edges, to write it in other
+/// formats. Serializes to `{"nodes": [...], "edges": [{"from", "to",
+/// "number"}, ...]}`.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+pub struct Export<'a> {
+    pub nodes: Vec<&'a str>,
+    pub edges: Vec<Edge<'a>>,
+}
+
+impl Export<'_> {
+    /// Graphviz source, with the numbers of bags as edge labels
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph bags {\n");
+        for node in &self.nodes {
+            writeln!(dot, "    \"{}\";", node).unwrap();
+        }
+        for edge in &self.edges {
+            writeln!(
+                dot,
+                "    \"{}\" -> \"{}\" [label={}];",
+                edge.from, edge.to, edge.number
+            )
+            .unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// One way a bag ends up inside another: the outer bag, then every bag on
+/// the way in with the number of them in the bag before it
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ContainmentPath<'a> {
+    pub start: &'a str,
+    pub steps: Vec<(usize, &'a str)>,
+}
+
+impl ContainmentPath<'_> {
+    /// How many of the innermost bags the outer bag holds along this path,
+    /// `None` if that overflows
+    pub fn multiplicity(&self) -> Option<usize> {
+        self.steps
+            .iter()
+            .try_fold(1usize, |product, &(number, _)| product.checked_mul(number))
+    }
+}
+
+impl fmt::Display for ContainmentPath<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.start)?;
+        for (number, bag) in &self.steps {
+            write!(f, " -> {} {}", number, bag)?;
+        }
+        Ok(())
+    }
+}
+
+/// The bag rules as a graph, with an index from every bag to the bags that
+/// directly contain it.
+///
+/// Colours are numbered in alphabetical order; a colour that is only
+/// mentioned in the contents of other bags gets a node without contents.
+pub struct BagGraph {
+    colours: Vec<String>,
+    index: BTreeMap<String, usize>,
+    contents: Vec<Vec<(usize, usize)>>,
+    contained_by: Vec<Vec<usize>>,
+    totals: Vec<Total>,
+}
+
+impl BagGraph {
+    pub fn new(rules: &BTreeMap<String, Vec<BagSpec>>) -> BagGraph {
+        let mut index = BTreeMap::new();
+        for (bag, specs) in rules {
+            index.insert(bag.clone(), 0);
+            for spec in specs {
+                index.insert(spec.bagtype.clone(), 0);
+            }
+        }
+        let colours: Vec<String> = index.keys().cloned().collect();
+        for (node, id) in index.values_mut().enumerate() {
+            *id = node;
+        }
+
+        let mut contents = vec![Vec::new(); colours.len()];
+        let mut contained_by = vec![Vec::new(); colours.len()];
+        for (bag, specs) in rules {
+            let outer = index[bag];
+            for spec in specs {
+                let inner = index[&spec.bagtype];
+                contents[outer].push((spec.number, inner));
+                contained_by[inner].push(outer);
+            }
+        }
+        let totals = count_totals(&contents, &contained_by);
+        BagGraph {
+            colours,
+            index,
+            contents,
+            contained_by,
+            totals,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.colours.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colours.is_empty()
+    }
+
+    pub fn contains(&self, colour: &str) -> bool {
+        self.index.contains_key(colour)
+    }
+
+    pub fn colours(&self) -> impl Iterator<Item = &str> {
+        self.colours.iter().map(String::as_str)
+    }
+
+    /// The bags directly inside a bag of this colour, with their numbers
+    pub fn contents(&self, colour: &str) -> Option<impl Iterator<Item = (usize, &str)>> {
+        let node = *self.index.get(colour)?;
+        Some(
+            self.contents[node]
+                .iter()
+                .map(move |&(number, inner)| (number, self.colours[inner].as_str())),
+        )
+    }
+
+    /// Every colour of bag that can eventually contain a bag of this colour,
+    /// in alphabetical order
+    pub fn ancestors(&self, colour: &str) -> Option<Vec<&str>> {
+        let node = *self.index.get(colour)?;
+        Some(self.collect(node, |node| self.contained_by[node].iter().copied()))
+    }
+
+    /// Every colour of bag that can eventually end up inside a bag of this
+    /// colour, in alphabetical order
+    pub fn descendants(&self, colour: &str) -> Option<Vec<&str>> {
+        let node = *self.index.get(colour)?;
+        Some(self.collect(node, |node| {
+            self.contents[node].iter().map(|&(_, inner)| inner)
+        }))
+    }
+
+    /// The total number of bags inside a bag of this colour
+    pub fn total_contents(&self, colour: &str) -> Option<Total> {
+        Some(self.totals[*self.index.get(colour)?])
+    }
+
+    /// The total number of bags inside a bag, for every colour
+    pub fn totals(&self) -> impl Iterator<Item = (&str, Total)> {
+        self.colours().zip(self.totals.iter().copied())
+    }
+
+    /// The whole graph, or only the bags that can end up inside a bag of
+    /// colour `from` (and that bag itself)
+    pub fn export(&self, from: Option<&str>) -> Option<Export<'_>> {
+        let included = match from {
+            Some(colour) => {
+                let node = *self.index.get(colour)?;
+                let mut included = vec![false; self.len()];
+                let mut stack = vec![node];
+                while let Some(node) = stack.pop() {
+                    if !included[node] {
+                        included[node] = true;
+                        stack.extend(self.contents[node].iter().map(|&(_, inner)| inner));
+                    }
+                }
+                included
+            }
+            None => vec![true; self.len()],
+        };
+
+        let nodes = (0..self.len()).filter(|&node| included[node]);
+        let edges = nodes
+            .clone()
+            .flat_map(|node| {
+                self.contents[node]
+                    .iter()
+                    .map(move |&(number, inner)| Edge {
+                        from: &self.colours[node],
+                        to: &self.colours[inner],
+                        number,
+                    })
+            })
+            .collect();
+        Some(Export {
+            nodes: nodes.map(|node| self.colours[node].as_str()).collect(),
+            edges,
+        })
+    }
+
+    /// Every way a bag of colour `to` ends up inside a bag of colour `from`,
+    /// without going through the same bag twice. `None` if there is no rule
+    /// about either colour.
+    pub fn paths(&self, from: &str, to: &str) -> Option<Vec<ContainmentPath<'_>>> {
+        let start = *self.index.get(from)?;
+        let end = *self.index.get(to)?;
+        let leads_to_end = self.leads_to(end);
+
+        let mut paths = Vec::new();
+        let mut on_path = vec![false; self.len()];
+        // the path being searched, with the next edge to follow from each bag
+        let mut path = vec![(start, 0)];
+        on_path[start] = true;
+        while let Some(&mut (node, ref mut edge)) = path.last_mut() {
+            match self.contents[node].get(*edge) {
+                Some(&(_, inner)) => {
+                    *edge += 1;
+                    if inner == end {
+                        paths.push(
+                            self.containment_path(
+                                path.iter().map(|&(node, edge)| (node, edge - 1)),
+                            ),
+                        );
+                    } else if leads_to_end[inner] && !on_path[inner] {
+                        on_path[inner] = true;
+                        path.push((inner, 0));
+                    }
+                }
+                None => {
+                    on_path[node] = false;
+                    path.pop();
+                }
+            }
+        }
+        Some(paths)
+    }
+
+    /// The way a bag of colour `to` ends up inside a bag of colour `from`
+    /// through the fewest bags
+    pub fn shortest_path(&self, from: &str, to: &str) -> Option<ContainmentPath<'_>> {
+        let start = *self.index.get(from)?;
+        let end = *self.index.get(to)?;
+
+        // for every bag that was found, the bag it was found in and the edge
+        let mut found_through: Vec<Option<(usize, usize)>> = vec![None; self.len()];
+        let mut queue = VecDeque::from(vec![start]);
+        while let Some(node) = queue.pop_front() {
+            for (edge, &(_, inner)) in self.contents[node].iter().enumerate() {
+                if inner == end {
+                    let mut edges = vec![(node, edge)];
+                    while let Some(previous) = found_through[edges.last().unwrap().0] {
+                        edges.push(previous);
+                    }
+                    return Some(self.containment_path(edges.into_iter().rev()));
+                }
+                if inner != start && found_through[inner].is_none() {
+                    found_through[inner] = Some((node, edge));
+                    queue.push_back(inner);
+                }
+            }
+        }
+        None
+    }
+
+    /// The way a bag of colour `to` ends up inside a bag of colour `from`
+    /// through the most bags. Only answered when bags of colour `from` don't
+    /// eventually contain themselves, as the longest path without repeats is
+    /// hard to find otherwise.
+    pub fn longest_path(&self, from: &str, to: &str) -> Option<ContainmentPath<'_>> {
+        let start = *self.index.get(from)?;
+        let end = *self.index.get(to)?;
+        if self.totals[start] == Total::Cyclic {
+            return None;
+        }
+
+        // for every bag, the longest path to the end and its first edge
+        fn longest(
+            graph: &BagGraph,
+            node: usize,
+            end: usize,
+            cache: &mut Vec<Option<Option<(usize, usize)>>>,
+        ) -> Option<(usize, usize)> {
+            if let Some(result) = cache[node] {
+                return result;
+            }
+            let mut result = None;
+            for (edge, &(_, inner)) in graph.contents[node].iter().enumerate() {
+                let length = if inner == end {
+                    Some(1)
+                } else {
+                    longest(graph, inner, end, cache).map(|(length, _)| length + 1)
+                };
+                if let Some(length) = length {
+                    if result.is_none_or(|(longest, _)| length > longest) {
+                        result = Some((length, edge));
+                    }
+                }
+            }
+            cache[node] = Some(result);
+            result
+        }
+
+        let mut cache = vec![None; self.len()];
+        let mut edges = Vec::new();
+        let mut node = start;
+        while let Some((_, edge)) = longest(self, node, end, &mut cache) {
+            edges.push((node, edge));
+            node = self.contents[node][edge].1;
+            if node == end {
+                break;
+            }
+        }
+        if edges.is_empty() {
+            None
+        } else {
+            Some(self.containment_path(edges.into_iter()))
+        }
+    }
+
+    /// The path that follows edge `edge` from bag `node` for every pair
+    fn containment_path(&self, edges: impl Iterator<Item = (usize, usize)>) -> ContainmentPath<'_> {
+        let mut edges = edges.peekable();
+        let start = edges.peek().map(|&(node, _)| node).unwrap();
+        ContainmentPath {
+            start: &self.colours[start],
+            steps: edges
+                .map(|(node, edge)| {
+                    let (number, inner) = self.contents[node][edge];
+                    (number, self.colours[inner].as_str())
+                })
+                .collect(),
+        }
+    }
+
+    /// For every bag, whether it is or can eventually contain `node`
+    fn leads_to(&self, node: usize) -> Vec<bool> {
+        let mut result = vec![false; self.len()];
+        let mut stack = vec![node];
+        while let Some(node) = stack.pop() {
+            if !result[node] {
+                result[node] = true;
+                stack.extend(&self.contained_by[node]);
+            }
+        }
+        result
+    }
+
+    /// Bags that eventually contain themselves. Every cycle is found through
+    /// a different edge back to a bag that is being searched, so cycles that
+    /// share that edge are only reported once.
+    pub fn cycles(&self) -> Vec<Vec<&str>> {
+        const UNSEEN: u8 = 0;
+        const SEARCHING: u8 = 1;
+        const DONE: u8 = 2;
+
+        let mut cycles = Vec::new();
+        let mut state = vec![UNSEEN; self.len()];
+        for root in 0..self.len() {
+            if state[root] != UNSEEN {
+                continue;
+            }
+            // the path being searched, with the next edge to follow from each bag
+            let mut path = vec![(root, 0)];
+            state[root] = SEARCHING;
+            while let Some(&mut (node, ref mut edge)) = path.last_mut() {
+                match self.contents[node].get(*edge) {
+                    Some(&(_, inner)) => {
+                        *edge += 1;
+                        if state[inner] == UNSEEN {
+                            state[inner] = SEARCHING;
+                            path.push((inner, 0));
+                        } else if state[inner] == SEARCHING {
+                            let start = path.iter().position(|&(bag, _)| bag == inner).unwrap();
+                            let cycle = path[start..]
+                                .iter()
+                                .chain(std::iter::once(&(inner, 0)))
+                                .map(|&(bag, _)| self.colours[bag].as_str())
+                                .collect();
+                            cycles.push(cycle);
+                        }
+                    }
+                    None => {
+                        state[node] = DONE;
+                        path.pop();
+                    }
+                }
+            }
+        }
+        cycles
+    }
+
+    /// The colours reachable from `start` over `edges`, without `start` itself
+    fn collect<E, It>(&self, start: usize, edges: E) -> Vec<&str>
+    where
+        E: Fn(usize) -> It,
+        It: Iterator<Item = usize>,
+    {
+        let mut seen = vec![false; self.len()];
+        let mut stack: Vec<usize> = edges(start).collect();
+        while let Some(node) = stack.pop() {
+            if !seen[node] {
+                seen[node] = true;
+                stack.extend(edges(node));
+            }
+        }
+        (0..self.len())
+            .filter(|&node| seen[node] && node != start)
+            .map(|node| self.colours[node].as_str())
+            .collect()
+    }
+}
+
+/// The totals for all bags, counted in topological order: a bag is counted
+/// once all bags inside it are, starting from the empty ones. The bags that
+/// are never counted contain a cycle.
+fn count_totals(contents: &[Vec<(usize, usize)>], contained_by: &[Vec<usize>]) -> Vec<Total> {
+    let mut totals = vec![Total::Cyclic; contents.len()];
+    let mut uncounted: Vec<usize> = contents.iter().map(Vec::len).collect();
+    let mut ready: Vec<usize> = (0..contents.len())
+        .filter(|&node| uncounted[node] == 0)
+        .collect();
+    while let Some(node) = ready.pop() {
+        totals[node] = contents[node]
+            .iter()
+            .try_fold(0usize, |sum, &(number, inner)| match totals[inner] {
+                Total::Bags(total) => total
+                    .checked_add(1)
+                    .and_then(|bags| bags.checked_mul(number))
+                    .and_then(|bags| bags.checked_add(sum)),
+                _ => None,
+            })
+            .map_or(Total::Overflow, Total::Bags);
+        for &outer in &contained_by[node] {
+            uncounted[outer] -= 1;
+            if uncounted[outer] == 0 {
+                ready.push(outer);
+            }
+        }
+    }
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(rules: &[(&str, &[(usize, &str)])]) -> Vec<Rule> {
+        rules
+            .iter()
+            .map(|(bag, specs)| {
+                let specs = specs
+                    .iter()
+                    .map(|&(number, bagtype)| BagSpec {
+                        number,
+                        bagtype: bagtype.to_string(),
+                    })
+                    .collect();
+                (bag.to_string(), specs)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn example() {
+        let rules = rules(&[
+            ("light red", &[(1, "bright white"), (2, "muted yellow")]),
+            ("dark orange", &[(3, "bright white"), (4, "muted yellow")]),
+            ("bright white", &[(1, "shiny gold")]),
+            ("muted yellow", &[(2, "shiny gold"), (9, "faded blue")]),
+            ("shiny gold", &[(1, "dark olive"), (2, "vibrant plum")]),
+            ("dark olive", &[(3, "faded blue"), (4, "dotted black")]),
+            ("vibrant plum", &[(5, "faded blue"), (6, "dotted black")]),
+            ("faded blue", &[]),
+            ("dotted black", &[]),
+        ]);
+        assert_eq!(validate(&rules), Vec::new());
+        let graph = BagGraph::new(&rules.into_iter().collect());
+        assert_eq!(
+            graph.ancestors("shiny gold").unwrap(),
+            vec!["bright white", "dark orange", "light red", "muted yellow"]
+        );
+        assert_eq!(
+            graph.descendants("shiny gold").unwrap(),
+            vec!["dark olive", "dotted black", "faded blue", "vibrant plum"]
+        );
+        assert_eq!(graph.total_contents("shiny gold"), Some(Total::Bags(32)));
+        assert_eq!(graph.total_contents("faded blue"), Some(Total::Bags(0)));
+        assert_eq!(graph.ancestors("light red").unwrap(), Vec::<&str>::new());
+        assert_eq!(graph.ancestors("pink"), None);
+
+        let export = graph.export(Some("dark olive")).unwrap();
+        assert_eq!(
+            export.nodes,
+            vec!["dark olive", "dotted black", "faded blue"]
+        );
+        assert_eq!(
+            export.to_dot(),
+            "digraph bags {
+    \"dark olive\";
+    \"dotted black\";
+    \"faded blue\";
+    \"dark olive\" -> \"faded blue\" [label=3];
+    \"dark olive\" -> \"dotted black\" [label=4];
+}
+"
+        );
+        assert_eq!(graph.export(None).unwrap().edges.len(), 13);
+
+        let paths: Vec<String> = graph
+            .paths("light red", "faded blue")
+            .unwrap()
+            .iter()
+            .map(|path| path.to_string())
+            .collect();
+        assert_eq!(
+            paths,
+            vec![
+                "light red -> 1 bright white -> 1 shiny gold -> 1 dark olive -> 3 faded blue",
+                "light red -> 1 bright white -> 1 shiny gold -> 2 vibrant plum -> 5 faded blue",
+                "light red -> 2 muted yellow -> 2 shiny gold -> 1 dark olive -> 3 faded blue",
+                "light red -> 2 muted yellow -> 2 shiny gold -> 2 vibrant plum -> 5 faded blue",
+                "light red -> 2 muted yellow -> 9 faded blue",
+            ]
+        );
+        let shortest = graph.shortest_path("light red", "faded blue").unwrap();
+        assert_eq!(shortest.to_string(), paths[4]);
+        assert_eq!(shortest.multiplicity(), Some(18));
+        let longest = graph.longest_path("light red", "faded blue").unwrap();
+        assert_eq!(longest.to_string(), paths[0]);
+        assert_eq!(graph.paths("faded blue", "light red"), Some(Vec::new()));
+        assert_eq!(graph.shortest_path("faded blue", "light red"), None);
+        assert_eq!(graph.longest_path("faded blue", "light red"), None);
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let rules = rules(&[
+            ("light red", &[(1, "bright white")]),
+            ("bright white", &[(2, "muted yellow"), (1, "shiny gold")]),
+            ("muted yellow", &[(3, "light red"), (1, "bright white")]),
+            ("light red", &[(1, "bright white")]),
+        ]);
+        let path = |bags: &[&str]| bags.iter().map(|bag| bag.to_string()).collect();
+        assert_eq!(
+            validate(&rules),
+            vec![
+                RuleError::Duplicate {
+                    bag: "light red".to_string(),
+                    rules: vec![1, 4],
+                },
+                RuleError::Dangling {
+                    bag: "bright white".to_string(),
+                    missing: "shiny gold".to_string(),
+                },
+                RuleError::Cycle(path(&[
+                    "bright white",
+                    "muted yellow",
+                    "light red",
+                    "bright white"
+                ])),
+                RuleError::Cycle(path(&["bright white", "muted yellow", "bright white"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn totals_overflow_and_cycles() {
+        let mut rules: BTreeMap<String, Vec<BagSpec>> = rules(&[
+            ("light red", &[(2, "bright white")]),
+            ("dark orange", &[(1, "light red"), (1, "faded blue")]),
+            ("bright white", &[]),
+            ("faded blue", &[(1, "dotted black")]),
+            ("dotted black", &[(1, "faded blue")]),
+        ])
+        .into_iter()
+        .collect();
+        rules.get_mut("light red").unwrap()[0].number = usize::MAX / 2;
+        let graph = BagGraph::new(&rules);
+        assert_eq!(
+            graph.total_contents("light red"),
+            Some(Total::Bags(usize::MAX / 2))
+        );
+        assert_eq!(graph.total_contents("dark orange"), Some(Total::Cyclic));
+        assert_eq!(graph.total_contents("dotted black"), Some(Total::Cyclic));
+        let paths = graph.paths("dark orange", "dotted black").unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(
+            graph
+                .shortest_path("faded blue", "faded blue")
+                .unwrap()
+                .steps
+                .len(),
+            2
+        );
+        assert_eq!(graph.longest_path("dark orange", "dotted black"), None);
+
+        rules.get_mut("bright white").unwrap().push(BagSpec {
+            number: 2,
+            bagtype: "shiny gold".to_string(),
+        });
+        let graph = BagGraph::new(&rules);
+        assert_eq!(graph.total_contents("bright white"), Some(Total::Bags(2)));
+        assert_eq!(graph.total_contents("light red"), Some(Total::Overflow));
+    }
+}
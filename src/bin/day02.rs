@@ -1,29 +1,43 @@
-use combine::parser::char::{char, digit, letter, spaces};
+use combine::parser::char::{char, digit, spaces};
 use combine::parser::repeat::many1;
+use combine::parser::token::satisfy;
 use combine::stream::position;
-use combine::{from_str, EasyParser, Parser};
+use combine::{eof, from_str, EasyParser, Parser};
 use gumdrop::Options;
-use std::convert::TryInto;
+use std::fmt;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader};
 
 struct PasswordEntry {
+    /// the 1-based line number in the input
+    line: usize,
     lower_bound: usize,
     upper_bound: usize,
     character: char,
     password: String,
 }
 
-fn parse(filepath: &str) -> Vec<PasswordEntry> {
+/// A line that isn't a password entry, and why
+#[derive(Debug)]
+struct ParseError {
+    line: usize,
+    message: String,
+}
+
+/// Parse a line such as `1-3 a: abcde`. The character and the password can
+/// be anything but whitespace.
+fn parse_entry(line: usize, text: &str) -> Result<PasswordEntry, ParseError> {
     let num_usize = || from_str(many1::<String, _, _>(digit()));
-    let entry = (
+    let non_space = || satisfy(|c: char| !c.is_whitespace());
+    let mut entry = (
         num_usize().skip(char('-')),
         num_usize().skip(spaces()),
-        letter().skip(char(':')).skip(spaces()),
-        many1(letter()).skip(spaces()),
+        non_space().skip(char(':')).skip(spaces()),
+        many1(non_space()).skip(spaces()).skip(eof()),
     )
         .map(
             |(lower_bound, upper_bound, character, password)| PasswordEntry {
+                line,
                 lower_bound,
                 upper_bound,
                 character,
@@ -31,49 +45,105 @@ fn parse(filepath: &str) -> Vec<PasswordEntry> {
             },
         );
 
-    let mut input = String::new();
-    let mut entries = many1(entry);
+    match entry.easy_parse(position::Stream::new(text)) {
+        Ok((entry, _)) => Ok(entry),
+        Err(error) => {
+            let reasons: Vec<String> = error
+                .errors
+                .iter()
+                .map(|reason| reason.to_string().trim_end().to_string())
+                .collect();
+            Err(ParseError {
+                line,
+                message: format!("column {}: {}", error.position.column, reasons.join(", ")),
+            })
+        }
+    }
+}
 
+/// The entries on all lines that could be parsed, and the errors for the
+/// others. Empty lines are skipped.
+fn parse(filepath: &str) -> (Vec<PasswordEntry>, Vec<ParseError>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
     match File::open(filepath) {
         Ok(file) => {
-            let mut bufreader = std::io::BufReader::new(file);
-            if bufreader.read_to_string(&mut input).is_ok() {
-                match entries.easy_parse(position::Stream::new(&*input)) {
-                    Ok((val, _)) => val,
-                    Err(err) => {
-                        println!("Parse error: {}", err);
-                        Vec::new()
+            for (index, text) in BufReader::new(file).lines().enumerate() {
+                let text = match text {
+                    Ok(text) => text,
+                    Err(error) => {
+                        println!("could not read file '{}': {}", filepath, error);
+                        return (Vec::new(), Vec::new());
                     }
+                };
+                if text.trim().is_empty() {
+                    continue;
+                }
+                match parse_entry(index + 1, text.trim()) {
+                    Ok(entry) => entries.push(entry),
+                    Err(error) => errors.push(error),
                 }
-            } else {
-                println!("could not open file '{}'", filepath);
-                Vec::new()
             }
         }
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
-            Vec::new()
         }
     }
+    (entries, errors)
 }
 
-fn password_is_valid(entry: &PasswordEntry) -> bool {
-    let count = entry
-        .password
-        .chars()
-        .filter(|&c| c == entry.character)
-        .count();
-    count >= entry.lower_bound && count <= entry.upper_bound
+impl fmt::Display for PasswordEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}-{} {}: {}",
+            self.lower_bound, self.upper_bound, self.character, self.password
+        )
+    }
 }
 
-fn num_passwords_valid(entries: &[PasswordEntry]) -> usize {
-    entries.iter().filter(|e| password_is_valid(e)).count()
+/// A rule that passwords have to follow
+trait PasswordPolicy: fmt::Debug {
+    /// How to select the policy with `--policy`
+    fn name(&self) -> String;
+    fn is_valid(&self, entry: &PasswordEntry) -> bool;
 }
 
-fn password_is_valid_otcas(entry: &PasswordEntry) -> bool {
-    if entry.lower_bound <= entry.password.len().try_into().unwrap()
-        && entry.upper_bound <= entry.password.len()
-    {
+/// The character of the entry occurs from lower bound to upper bound times
+#[derive(Debug)]
+struct CountPolicy;
+
+impl PasswordPolicy for CountPolicy {
+    fn name(&self) -> String {
+        String::from("count")
+    }
+
+    fn is_valid(&self, entry: &PasswordEntry) -> bool {
+        let count = entry
+            .password
+            .chars()
+            .filter(|&c| c == entry.character)
+            .count();
+        count >= entry.lower_bound && count <= entry.upper_bound
+    }
+}
+
+/// The character of the entry is at exactly one of the two (1-based)
+/// positions, as the Official Toboggan Corporate Authentication System wants.
+/// A position past the end of the password doesn't match, and there is no
+/// position 0, so an entry with a zero position is never valid.
+#[derive(Debug)]
+struct PositionPolicy;
+
+impl PasswordPolicy for PositionPolicy {
+    fn name(&self) -> String {
+        String::from("position")
+    }
+
+    fn is_valid(&self, entry: &PasswordEntry) -> bool {
+        if entry.lower_bound == 0 || entry.upper_bound == 0 {
+            return false;
+        }
         entry
             .password
             .chars()
@@ -83,26 +153,287 @@ fn password_is_valid_otcas(entry: &PasswordEntry) -> bool {
             })
             .count()
             == 1
-    } else {
-        false
     }
 }
 
-fn num_passwords_valid_otcas(entries: &[PasswordEntry]) -> usize {
-    entries.iter().filter(|e| password_is_valid_otcas(e)).count()
+/// At least this many characters
+#[derive(Debug)]
+struct MinLengthPolicy(usize);
+
+impl PasswordPolicy for MinLengthPolicy {
+    fn name(&self) -> String {
+        format!("min-length={}", self.0)
+    }
+
+    fn is_valid(&self, entry: &PasswordEntry) -> bool {
+        entry.password.chars().count() >= self.0
+    }
+}
+
+/// Characters from at least this many of the classes lowercase, uppercase,
+/// digits and other characters
+#[derive(Debug)]
+struct ClassesPolicy(usize);
+
+impl PasswordPolicy for ClassesPolicy {
+    fn name(&self) -> String {
+        format!("classes={}", self.0)
+    }
+
+    fn is_valid(&self, entry: &PasswordEntry) -> bool {
+        let mut classes = [false; 4];
+        for c in entry.password.chars() {
+            let class = if c.is_lowercase() {
+                0
+            } else if c.is_uppercase() {
+                1
+            } else if c.is_ascii_digit() {
+                2
+            } else {
+                3
+            };
+            classes[class] = true;
+        }
+        classes.iter().filter(|&&used| used).count() >= self.0
+    }
+}
+
+/// No character more than this many times in a row
+#[derive(Debug)]
+struct MaxRepeatPolicy(usize);
+
+impl PasswordPolicy for MaxRepeatPolicy {
+    fn name(&self) -> String {
+        format!("max-repeat={}", self.0)
+    }
+
+    fn is_valid(&self, entry: &PasswordEntry) -> bool {
+        let mut previous = None;
+        let mut run = 0;
+        for c in entry.password.chars() {
+            run = if previous == Some(c) { run + 1 } else { 1 };
+            if run > self.0 {
+                return false;
+            }
+            previous = Some(c);
+        }
+        true
+    }
+}
+
+fn parse_policy(policy: &str) -> Result<Box<dyn PasswordPolicy>, String> {
+    let (name, argument) = match policy.find('=') {
+        Some(index) => (&policy[..index], Some(&policy[index + 1..])),
+        None => (policy, None),
+    };
+    let number = || match argument.map(str::parse::<usize>) {
+        Some(Ok(number)) => Ok(number),
+        _ => Err(format!(
+            "policy '{}' needs a number, as in '{}=3'",
+            name, name
+        )),
+    };
+    match name {
+        "count" => Ok(Box::new(CountPolicy)),
+        "position" => Ok(Box::new(PositionPolicy)),
+        "min-length" => Ok(Box::new(MinLengthPolicy(number()?))),
+        "classes" => Ok(Box::new(ClassesPolicy(number()?))),
+        "max-repeat" => Ok(Box::new(MaxRepeatPolicy(number()?))),
+        _ => Err(format!(
+            "unknown policy '{}', expected count, position, min-length=N, classes=N or max-repeat=N",
+            name
+        )),
+    }
+}
+
+fn num_passwords_valid(entries: &[PasswordEntry], policy: &dyn PasswordPolicy) -> usize {
+    entries.iter().filter(|e| policy.is_valid(e)).count()
+}
+
+/// Print every entry that fails one of the policies, with the policies it fails
+fn print_failures(entries: &[PasswordEntry], policies: &[Box<dyn PasswordPolicy>]) {
+    for entry in entries {
+        let failed: Vec<String> = policies
+            .iter()
+            .filter(|policy| !policy.is_valid(entry))
+            .map(|policy| policy.name())
+            .collect();
+        if !failed.is_empty() {
+            println!(
+                "line {} ({}) fails {}",
+                entry.line,
+                entry,
+                failed.join(", ")
+            );
+        }
+    }
 }
 
 #[derive(Debug, Options)]
 struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
     #[options(free)]
     input_file: String,
+    #[options(
+        help = "check this policy instead of the puzzle parts: count, position, min-length=N, classes=N or max-repeat=N (repeatable)",
+        parse(try_from_str = "parse_policy")
+    )]
+    policy: Vec<Box<dyn PasswordPolicy>>,
+    #[options(help = "list the entries that fail, with the policies they fail")]
+    report: bool,
 }
 
 fn main() {
     let opts = Arguments::parse_args_default_or_exit();
-    let entries = parse(&opts.input_file);
-    if !entries.is_empty() {
-        println!("Part 1: {}", num_passwords_valid(&entries));
-        println!("Part 2: {}", num_passwords_valid_otcas(&entries));
+    let (entries, errors) = parse(&opts.input_file);
+    for error in &errors {
+        println!("line {}: {}", error.line, error.message);
+    }
+    if entries.is_empty() {
+        return;
+    }
+
+    if opts.policy.is_empty() {
+        println!("Part 1: {}", num_passwords_valid(&entries, &CountPolicy));
+        println!("Part 2: {}", num_passwords_valid(&entries, &PositionPolicy));
+    } else {
+        for policy in &opts.policy {
+            println!(
+                "{}: {}",
+                policy.name(),
+                num_passwords_valid(&entries, policy.as_ref())
+            );
+        }
+        let all = entries
+            .iter()
+            .filter(|entry| opts.policy.iter().all(|policy| policy.is_valid(entry)))
+            .count();
+        println!("all policies: {}", all);
+    }
+    if opts.report {
+        let policies: Vec<Box<dyn PasswordPolicy>> = if opts.policy.is_empty() {
+            vec![Box::new(CountPolicy), Box::new(PositionPolicy)]
+        } else {
+            opts.policy
+        };
+        print_failures(&entries, &policies);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(password: &str) -> PasswordEntry {
+        PasswordEntry {
+            line: 1,
+            lower_bound: 1,
+            upper_bound: 3,
+            character: 'a',
+            password: password.to_string(),
+        }
+    }
+
+    #[test]
+    fn policies() {
+        let names = [
+            "count",
+            "position",
+            "min-length=6",
+            "classes=2",
+            "max-repeat=2",
+        ];
+        let policies: Vec<Box<dyn PasswordPolicy>> = names
+            .iter()
+            .map(|policy| parse_policy(policy).unwrap())
+            .collect();
+        let failures = |password: &str| -> Vec<String> {
+            policies
+                .iter()
+                .filter(|policy| !policy.is_valid(&entry(password)))
+                .map(|policy| policy.name())
+                .collect()
+        };
+        assert_eq!(failures("abcde"), vec!["min-length=6", "classes=2"]);
+        assert_eq!(failures("cdefgA"), vec!["count", "position"]);
+        assert_eq!(
+            failures("aXaaa1"),
+            vec!["count", "position", "max-repeat=2"]
+        );
+        assert!(parse_policy("min-length=x").is_err());
+    }
+
+    #[test]
+    fn parse_errors() {
+        let entry = parse_entry(1, "2-4 #: p4$$#w0rd#").unwrap();
+        assert_eq!(
+            (entry.character, entry.password.as_str()),
+            ('#', "p4$$#w0rd#")
+        );
+        assert!(CountPolicy.is_valid(&entry));
+
+        let error = parse_entry(7, "2-x a: abc").err().unwrap();
+        assert_eq!(
+            (error.line, error.message.as_str()),
+            (7, "column 3: Unexpected `x`, Expected digit")
+        );
+        assert!(parse_entry(1, "1-3 a abc").is_err());
+        assert!(parse_entry(1, "1-3 a: abc def").is_err());
+    }
+
+    /// The positional policy as the puzzle states it, for one or two
+    /// positions
+    fn reference_position_policy(
+        lower: usize,
+        upper: usize,
+        character: char,
+        password: &str,
+    ) -> bool {
+        let chars: Vec<char> = password.chars().collect();
+        let matches = |position: usize| {
+            position >= 1 && position <= chars.len() && chars[position - 1] == character
+        };
+        if lower == 0 || upper == 0 {
+            false
+        } else if lower == upper {
+            matches(lower)
+        } else {
+            matches(lower) != matches(upper)
+        }
+    }
+
+    #[test]
+    fn position_policy_matches_reference() {
+        let mut passwords = vec![String::new()];
+        for length in 1..=6 {
+            for bits in 0..1 << length {
+                passwords.push(
+                    (0..length)
+                        .map(|i| if bits >> i & 1 == 1 { 'a' } else { 'b' })
+                        .collect(),
+                );
+            }
+        }
+        for password in &passwords {
+            for lower in 0..=8 {
+                for upper in 0..=8 {
+                    let entry = PasswordEntry {
+                        line: 1,
+                        lower_bound: lower,
+                        upper_bound: upper,
+                        character: 'a',
+                        password: password.clone(),
+                    };
+                    assert_eq!(
+                        PositionPolicy.is_valid(&entry),
+                        reference_position_policy(lower, upper, 'a', password),
+                        "{}",
+                        entry
+                    );
+                }
+            }
+        }
+        assert!(PositionPolicy.is_valid(&parse_entry(1, "2-9 a: bab").unwrap()));
     }
 }
@@ -1,59 +1,210 @@
-use std::io::prelude::*;
+use std::cmp::Reverse;
 use std::fs::File;
+use std::io::prelude::*;
 
 use gumdrop::Options;
 
 fn read_map(filepath: &str) -> Vec<Vec<u8>> {
     match File::open(filepath) {
-        Ok(file) => std::io::BufReader::new(file).lines().filter_map(|line| line.ok().map(|l| l.bytes().collect())).collect(),
+        Ok(file) => std::io::BufReader::new(file)
+            .lines()
+            .map_while(Result::ok)
+            .map(|l| l.bytes().collect())
+            .collect(),
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
             Vec::new()
         }
-    } 
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Slope {
+    right: usize,
+    down: usize,
+}
+
+impl Slope {
+    fn new(right: usize, down: usize) -> Slope {
+        Slope { right, down }
+    }
+}
+
+fn parse_slope(s: &str) -> Result<Slope, String> {
+    let mut parts = s.split(',');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(right), Some(down), None) => {
+            let right = right
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid right step '{}'", right))?;
+            let down = down
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid down step '{}'", down))?;
+            if down == 0 {
+                Err(String::from("down step must be at least 1"))
+            } else {
+                Ok(Slope::new(right, down))
+            }
+        }
+        _ => Err(format!("expected a slope as 'right,down', got '{}'", s)),
+    }
+}
+
+/// All slopes from `from` up to `to`, inclusive in both directions
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct SlopeRange {
+    from: Slope,
+    to: Slope,
+}
+
+fn parse_slope_range(s: &str) -> Result<SlopeRange, String> {
+    let (from, to) = match s.find("..") {
+        Some(index) => (parse_slope(&s[..index])?, parse_slope(&s[index + 2..])?),
+        None => (Slope::new(1, 1), parse_slope(s)?),
+    };
+    if from.right > to.right || from.down > to.down {
+        Err(format!("empty slope range '{}'", s))
+    } else {
+        Ok(SlopeRange { from, to })
+    }
 }
 
 #[derive(Debug, Options)]
 struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
     #[options(free)]
-    input_file: String
+    input_file: String,
+    #[options(
+        help = "report trees for this slope, as right,down (repeatable)",
+        parse(try_from_str = "parse_slope")
+    )]
+    slope: Vec<Slope>,
+    #[options(
+        help = "report trees for all slopes up to right,down, starting from 1,1 or from a lower bound given as right,down..right,down",
+        parse(try_from_str = "parse_slope_range")
+    )]
+    search: Option<SlopeRange>,
 }
 
-fn num_trees_with_slope(map: &[Vec<u8>], right_step: usize, down_step: usize) -> usize {
-    let mut count = 0;
+fn num_trees_with_slope(map: &[Vec<u8>], slope: Slope) -> usize {
+    map.iter()
+        .step_by(slope.down)
+        .enumerate()
+        .filter(|(step, row)| !row.is_empty() && row[(step * slope.right) % row.len()] == b'#')
+        .count()
+}
 
-    let mut pos = (0,0);
-    let length = map.len();
-    if length > 0 {
-        let width = map[0].len();
-        loop {
-            if pos.0 >= length {
-                break;
-            }
-            pos.1 = pos.1 % width;
+fn search_slopes(range: SlopeRange) -> Vec<Slope> {
+    let SlopeRange { from, to } = range;
+    (from.down..=to.down)
+        .flat_map(|down| (from.right..=to.right).map(move |right| Slope::new(right, down)))
+        .collect()
+}
 
-            if map[pos.0][pos.1] == '#' as u8 {
-                count += 1
-            }
+/// A slope and the number of trees on it
+type SlopeCount = (Slope, usize);
 
-            pos = (pos.0 + down_step, pos.1 + right_step);
-        }
+/// The slopes with the fewest and the most trees; on a tie the one listed
+/// first wins in both cases
+fn fewest_and_most(counts: &[SlopeCount]) -> Option<(&SlopeCount, &SlopeCount)> {
+    let fewest = counts.iter().min_by_key(|(_, count)| count)?;
+    let (_, most) = counts
+        .iter()
+        .enumerate()
+        .max_by_key(|(index, (_, count))| (count, Reverse(*index)))?;
+    Some((fewest, most))
+}
+
+fn report(map: &[Vec<u8>], slopes: &[Slope]) {
+    let counts: Vec<SlopeCount> = slopes
+        .iter()
+        .map(|&slope| (slope, num_trees_with_slope(map, slope)))
+        .collect();
+    for (slope, count) in &counts {
+        println!("right {}, down {}: {}", slope.right, slope.down, count);
     }
 
-    count
+    if let Some(((fewest, fewest_count), (most, most_count))) = fewest_and_most(&counts) {
+        println!(
+            "Fewest trees: right {}, down {} ({})",
+            fewest.right, fewest.down, fewest_count
+        );
+        println!(
+            "Most trees: right {}, down {} ({})",
+            most.right, most.down, most_count
+        );
+    }
 }
 
 fn main() {
     let opts = Arguments::parse_args_default_or_exit();
     let map = read_map(&opts.input_file);
-    if !map.is_empty() {
-        println!("Part 1: {}", num_trees_with_slope(&map, 3, 1));
-        let part2 = num_trees_with_slope(&map, 1, 1) 
-                  * num_trees_with_slope(&map, 3 ,1)
-                  * num_trees_with_slope(&map, 5 ,1)
-                  * num_trees_with_slope(&map, 7 ,1)
-                  * num_trees_with_slope(&map, 1 ,2);
+    if !opts.slope.is_empty() || opts.search.is_some() {
+        let mut slopes = opts.slope.clone();
+        if let Some(range) = opts.search {
+            slopes.extend(search_slopes(range));
+        }
+        report(&map, &slopes);
+    } else if !map.is_empty() {
+        println!("Part 1: {}", num_trees_with_slope(&map, Slope::new(3, 1)));
+        let part2: usize = [(1, 1), (3, 1), (5, 1), (7, 1), (1, 2)]
+            .iter()
+            .map(|&(right, down)| num_trees_with_slope(&map, Slope::new(right, down)))
+            .product();
         println!("Part 2: {}", part2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_map() {
+        assert_eq!(num_trees_with_slope(&[], Slope::new(3, 1)), 0);
+        assert_eq!(
+            num_trees_with_slope(&[Vec::new(), Vec::new()], Slope::new(3, 1)),
+            0
+        );
+    }
+
+    #[test]
+    fn slope_parsing() {
+        assert_eq!(parse_slope("3,1"), Ok(Slope::new(3, 1)));
+        assert!(parse_slope("3,0").is_err());
+        assert!(parse_slope("3").is_err());
+        assert!(parse_slope("3,1,2").is_err());
+    }
+
+    #[test]
+    fn slope_ranges() {
+        let range = parse_slope_range("2,1..3,2").unwrap();
+        let slopes = [
+            Slope::new(2, 1),
+            Slope::new(3, 1),
+            Slope::new(2, 2),
+            Slope::new(3, 2),
+        ];
+        assert_eq!(search_slopes(range), slopes);
+        assert_eq!(parse_slope_range("2,2"), parse_slope_range("1,1..2,2"));
+        assert!(parse_slope_range("3,1..2,2").is_err());
+        assert!(parse_slope_range("1,0..2,2").is_err());
+    }
 
+    #[test]
+    fn ties_go_to_the_first_slope() {
+        let counts = [
+            (Slope::new(1, 1), 2),
+            (Slope::new(2, 1), 5),
+            (Slope::new(3, 1), 2),
+            (Slope::new(4, 1), 5),
+        ];
+        let (fewest, most) = fewest_and_most(&counts).unwrap();
+        assert_eq!(fewest.0, Slope::new(1, 1));
+        assert_eq!(most.0, Slope::new(2, 1));
+        assert!(fewest_and_most(&[]).is_none());
     }
 }
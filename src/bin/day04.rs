@@ -1,107 +1,329 @@
 use std::collections::BTreeMap;
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::BufReader;
+
+use advent_of_code_2020::paragraphs::paragraphs;
 
 use gumdrop::Options;
 use regex::Regex;
+use serde::Deserialize;
+
+/// The schema for part 2, in the format `--schema` reads
+static DEFAULT_SCHEMA: &str = r##"
+[fields.byr]
+type = "year"
+min = 1920
+max = 2002
+
+[fields.iyr]
+type = "year"
+min = 2010
+max = 2020
+
+[fields.eyr]
+type = "year"
+min = 2020
+max = 2030
+
+[fields.hgt]
+type = "number"
+units = { cm = [150, 193], in = [59, 76] }
+
+[fields.hcl]
+type = "regex"
+pattern = "#[0-9a-f]{6}"
+
+[fields.ecl]
+type = "enum"
+values = ["amb", "blu", "brn", "gry", "grn", "hzl", "oth"]
+
+[fields.pid]
+type = "regex"
+pattern = "[0-9]{9}"
+
+[fields.cid]
+type = "any"
+required = false
+"##;
+
+/// A document type: the fields it has, read from a toml or json file
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Schema {
+    fields: BTreeMap<String, FieldSchema>,
+}
+
+#[derive(Deserialize)]
+struct FieldSchema {
+    #[serde(default = "required_by_default")]
+    required: bool,
+    #[serde(flatten)]
+    rule: Rule,
+}
+
+fn required_by_default() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase", deny_unknown_fields)]
+enum Rule {
+    /// any value is fine
+    Any,
+    /// four digits, from min to max inclusive
+    Year { min: u32, max: u32 },
+    /// digits followed by one of the units, with the range for that unit
+    /// (the unit "" allows a number without unit)
+    Number { units: BTreeMap<String, (u32, u32)> },
+    /// a regular expression that has to match the whole value
+    Regex { pattern: String },
+    /// one of the values
+    Enum { values: Vec<String> },
+}
+
+enum Check {
+    Any,
+    Year(u32, u32),
+    Number(BTreeMap<String, (u32, u32)>),
+    Regex(Regex),
+    Enum(Vec<String>),
+}
 
-fn has_match(regex: &Regex, passport: &BTreeMap<String, String>, field: &str) -> bool {
-    if let Some(value) = passport.get(field) {
-        regex.is_match(value)
+fn parse_digits(digits: &str) -> Option<u32> {
+    if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        digits.parse().ok()
     } else {
-        false
+        None
     }
 }
 
+impl Check {
+    fn new(rule: Rule) -> Result<Check, regex::Error> {
+        Ok(match rule {
+            Rule::Any => Check::Any,
+            Rule::Year { min, max } => Check::Year(min, max),
+            Rule::Number { units } => Check::Number(units),
+            Rule::Regex { pattern } => Check::Regex(Regex::new(&format!("^(?:{})$", pattern))?),
+            Rule::Enum { values } => Check::Enum(values),
+        })
+    }
+
+    /// Why the value breaks the rule, if it does
+    fn check(&self, value: &str) -> Result<(), String> {
+        match self {
+            Check::Any => Ok(()),
+            Check::Year(min, max) => match parse_digits(value) {
+                Some(year) if value.len() == 4 => in_range(value, year, *min, *max),
+                _ => Err(format!("'{}' is not a four digit year", value)),
+            },
+            Check::Number(units) => {
+                let split = value
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(value.len());
+                let (digits, unit) = value.split_at(split);
+                match (parse_digits(digits), units.get(unit)) {
+                    (Some(number), Some(&(min, max))) => in_range(value, number, min, max),
+                    (None, _) => Err(format!("'{}' does not start with a number", value)),
+                    (Some(_), None) => {
+                        let expected: Vec<String> =
+                            units.keys().map(|unit| format!("'{}'", unit)).collect();
+                        Err(format!(
+                            "'{}' has unit '{}', expected {}",
+                            value,
+                            unit,
+                            expected.join(" or ")
+                        ))
+                    }
+                }
+            }
+            Check::Regex(regex) => {
+                if regex.is_match(value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' does not match {}", value, regex))
+                }
+            }
+            Check::Enum(values) => {
+                if values.iter().any(|allowed| allowed == value) {
+                    Ok(())
+                } else {
+                    Err(format!("'{}' is not one of {}", value, values.join(", ")))
+                }
+            }
+        }
+    }
+}
+
+fn in_range(value: &str, number: u32, min: u32, max: u32) -> Result<(), String> {
+    if number < min {
+        Err(format!("{} below min {}", value, min))
+    } else if number > max {
+        Err(format!("{} above max {}", value, max))
+    } else {
+        Ok(())
+    }
+}
+
+struct Field {
+    name: String,
+    required: bool,
+    check: Check,
+}
+
 struct Validator {
-    num: Regex,
-    hgt: Regex,
-    hcl: Regex,
-    ecl: Regex,
-    pid: Regex,
+    fields: Vec<Field>,
 }
 
 impl Validator {
-    fn new() -> Self {
-        Validator {
-            num: Regex::new(r"^\d\d\d\d$").unwrap(),
-            hgt: Regex::new(r"^(\d\d\d?)(in|cm)$").unwrap(),
-            hcl: Regex::new(r"^#[0-9a-f]{6}$").unwrap(),
-            ecl: Regex::new(r"^(amb|blu|brn|gry|grn|hzl|oth)$").unwrap(),
-            pid: Regex::new(r"^\d{9}$").unwrap(),
+    fn new(schema: Schema) -> Result<Self, String> {
+        let mut fields = Vec::new();
+        for (name, field) in schema.fields {
+            let check =
+                Check::new(field.rule).map_err(|error| format!("field '{}': {}", name, error))?;
+            fields.push(Field {
+                name,
+                required: field.required,
+                check,
+            });
         }
+        Ok(Validator { fields })
     }
 
-    fn match_year(
-        &self,
-        passport: &BTreeMap<String, String>,
-        field: &str,
-        min_year: u32,
-        max_year: u32,
-    ) -> bool {
-        if let Some(value) = passport.get(field) {
-            if self.num.is_match(value) {
-                let num: u32 = value.parse().unwrap();
-                num >= min_year && num <= max_year
-            } else {
-                false
+    fn knows(&self, name: &str) -> bool {
+        self.fields.iter().any(|field| field.name == name)
+    }
+
+    fn has_required_fields(&self, passport: &BTreeMap<String, String>) -> bool {
+        self.fields
+            .iter()
+            .all(|field| !field.required || passport.contains_key(&field.name))
+    }
+
+    fn is_valid_passport(&self, passport: &BTreeMap<String, String>) -> bool {
+        self.problems(passport).is_empty()
+    }
+
+    /// Every field that is missing or breaks its rule, as "field: reason"
+    fn problems(&self, passport: &BTreeMap<String, String>) -> Vec<String> {
+        self.fields
+            .iter()
+            .filter_map(|field| match passport.get(&field.name) {
+                Some(value) => field
+                    .check
+                    .check(value)
+                    .err()
+                    .map(|reason| format!("{}: {}", field.name, reason)),
+                None if field.required => Some(format!("{}: missing", field.name)),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+fn parse_schema(input: &str, json: bool) -> Result<Validator, String> {
+    let schema: Schema = if json {
+        serde_json::from_str(input).map_err(|error| error.to_string())?
+    } else {
+        toml::from_str(input).map_err(|error| error.to_string())?
+    };
+    Validator::new(schema)
+}
+
+fn read_schema(filepath: &str) -> Option<Validator> {
+    match std::fs::read_to_string(filepath) {
+        Ok(input) => match parse_schema(&input, filepath.ends_with(".json")) {
+            Ok(validator) => Some(validator),
+            Err(error) => {
+                println!("could not parse schema '{}': {}", filepath, error);
+                None
             }
-        } else {
-            false
+        },
+        Err(error) => {
+            println!("could not open file '{}': {}", filepath, error);
+            None
         }
     }
+}
 
-    fn valid_height(&self, passport: &BTreeMap<String, String>) -> bool {
-        if let Some(value) = passport.get("hgt") {
-            if let Some(captures) = self.hgt.captures(value) {
-                let num: u32 = captures.get(1).unwrap().as_str().parse().unwrap();
-                if captures.get(2).unwrap().as_str() == "cm" {
-                    num >= 150 && num <= 193
-                } else {
-                    num >= 59 && num <= 76
-                }
-            } else {
-                false
+/// The fields of a passport, and the lines of the input it was on
+struct Passport {
+    first_line: usize,
+    last_line: usize,
+    /// when a field is given more than once, the last value
+    fields: BTreeMap<String, String>,
+    /// words that are not a `name:value` pair
+    malformed: Vec<String>,
+    /// the names of fields that are given more than once
+    duplicates: Vec<String>,
+}
+
+impl Passport {
+    fn parse<'a>(
+        first_line: usize,
+        last_line: usize,
+        words: impl Iterator<Item = &'a str>,
+    ) -> Passport {
+        let mut passport = Passport {
+            first_line,
+            last_line,
+            fields: BTreeMap::new(),
+            malformed: Vec::new(),
+            duplicates: Vec::new(),
+        };
+        for item in words {
+            let items = item.split(':').collect::<Vec<_>>();
+            if items.len() != 2 {
+                passport.malformed.push(String::from(item));
+            } else if passport
+                .fields
+                .insert(String::from(items[0]), String::from(items[1]))
+                .is_some()
+                && !passport.duplicates.iter().any(|name| name == items[0])
+            {
+                passport.duplicates.push(String::from(items[0]));
             }
-        } else {
-            false
         }
+        passport
     }
 
-    fn is_valid_passport(&self, passport: &BTreeMap<String, String>) -> bool {
-        self.match_year(passport, "byr", 1920, 2002)
-            && self.match_year(passport, "iyr", 2010, 2020)
-            && self.match_year(passport, "eyr", 2020, 2030)
-            && has_match(&self.hcl, passport, "hcl")
-            && has_match(&self.ecl, passport, "ecl")
-            && has_match(&self.pid, passport, "pid")
-            && self.valid_height(passport)
+    /// What strict parsing rejects: malformed words, duplicate fields and
+    /// fields the schema doesn't know
+    fn parse_problems(&self, validator: &Validator) -> Vec<String> {
+        let malformed = self
+            .malformed
+            .iter()
+            .map(|item| format!("'{}': not a name:value pair", item));
+        let duplicates = self
+            .duplicates
+            .iter()
+            .map(|name| format!("{}: given more than once", name));
+        let unknown = self
+            .fields
+            .keys()
+            .filter(|name| !validator.knows(name))
+            .map(|name| format!("{}: unknown field", name));
+        malformed.chain(duplicates).chain(unknown).collect()
     }
 }
 
-fn read_passports(filepath: &str) -> Vec<BTreeMap<String, String>> {
+fn read_passports(filepath: &str) -> Vec<Passport> {
     match File::open(filepath) {
-        Ok(mut file) => {
-            let mut input = String::new();
-            if file.read_to_string(&mut input).is_ok() {
-                input
-                    .split("\n\n")
-                    .map(|line| {
-                        line.split_whitespace()
-                            .filter_map(|item| {
-                                let items = item.split(':').collect::<Vec<_>>();
-                                if items.len() == 2 {
-                                    Some((String::from(items[0]), String::from(items[1])))
-                                } else {
-                                    None
-                                }
-                            })
-                            .collect()
-                    })
-                    .collect()
-            } else {
-                Vec::new()
+        Ok(file) => {
+            let mut passports = Vec::new();
+            for paragraph in paragraphs(BufReader::new(file)) {
+                match paragraph {
+                    Ok(paragraph) => passports.push(Passport::parse(
+                        paragraph.first_line,
+                        paragraph.last_line(),
+                        paragraph.words(),
+                    )),
+                    Err(error) => {
+                        println!("could not read file '{}': {}", filepath, error);
+                        return Vec::new();
+                    }
+                }
             }
+            passports
         }
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
@@ -112,28 +334,207 @@ fn read_passports(filepath: &str) -> Vec<BTreeMap<String, String>> {
 
 #[derive(Debug, Options)]
 struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
     #[options(free)]
     input_file: String,
+    #[options(help = "toml or json file with the fields of the document type to check")]
+    schema: Option<String>,
+    #[options(help = "list the problems with every passport that is rejected in part 2")]
+    report: bool,
+    #[options(help = "reject passports with malformed words, duplicate or unknown fields")]
+    strict: bool,
 }
 
-fn is_valid_passport(passport: &BTreeMap<String, String>) -> bool {
-    passport.len() == 8 || (passport.len() == 7 && !passport.contains_key("cid"))
+fn num_valid_passports(validator: &Validator, passports: &[Passport]) -> usize {
+    passports
+        .iter()
+        .filter(|p| validator.has_required_fields(&p.fields))
+        .count()
 }
 
-fn num_valid_passports(passports: &[BTreeMap<String, String>]) -> usize {
-    passports.iter().filter(|p| is_valid_passport(&p)).count()
+fn num_valid_passports_strict(validator: &Validator, passports: &[Passport]) -> usize {
+    passports
+        .iter()
+        .filter(|p| validator.is_valid_passport(&p.fields))
+        .count()
 }
 
-fn num_valid_passports_strict(validator: &Validator, passports: &[BTreeMap<String, String>]) -> usize {
-    passports.iter().filter(|p| validator.is_valid_passport(p)).count()
+/// Remove the passports that strict parsing rejects
+fn drop_malformed(validator: &Validator, passports: &mut Vec<Passport>) {
+    passports.retain(|passport| passport.parse_problems(validator).is_empty());
+}
+
+/// List the `problems` of every passport that has any
+fn print_report(passports: &[Passport], problems: impl Fn(&Passport) -> Vec<String>) {
+    for (index, passport) in passports.iter().enumerate() {
+        let problems = problems(passport);
+        if problems.is_empty() {
+            continue;
+        }
+        if passport.first_line == passport.last_line {
+            println!("passport {} (line {}):", index + 1, passport.first_line);
+        } else {
+            println!(
+                "passport {} (lines {}-{}):",
+                index + 1,
+                passport.first_line,
+                passport.last_line
+            );
+        }
+        for problem in problems {
+            println!("    {}", problem);
+        }
+    }
 }
 
 fn main() {
     let opts = Arguments::parse_args_default_or_exit();
-    let passports = read_passports(&opts.input_file);
-    if !passports.is_empty() {
-        println!("Part 1: {}", num_valid_passports(&passports));
-        let validator = Validator::new();
-        println!("Part 2: {}", num_valid_passports_strict(&validator, &passports));
+    let validator = match &opts.schema {
+        Some(filepath) => match read_schema(filepath) {
+            Some(validator) => validator,
+            None => return,
+        },
+        None => parse_schema(DEFAULT_SCHEMA, false).unwrap(),
+    };
+    let mut passports = read_passports(&opts.input_file);
+    if passports.is_empty() {
+        return;
+    }
+    if opts.report {
+        print_report(&passports, |passport| {
+            let mut problems = if opts.strict {
+                passport.parse_problems(&validator)
+            } else {
+                Vec::new()
+            };
+            problems.extend(validator.problems(&passport.fields));
+            problems
+        });
+    } else if opts.strict {
+        print_report(&passports, |passport| passport.parse_problems(&validator));
+    }
+    if opts.strict {
+        drop_malformed(&validator, &mut passports);
+    }
+    println!("Part 1: {}", num_valid_passports(&validator, &passports));
+    println!(
+        "Part 2: {}",
+        num_valid_passports_strict(&validator, &passports)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn passport(fields: &str) -> BTreeMap<String, String> {
+        fields
+            .split_whitespace()
+            .map(|field| {
+                let (name, value) = field.split_at(field.find(':').unwrap());
+                (name.to_string(), value[1..].to_string())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn default_schema() {
+        let validator = parse_schema(DEFAULT_SCHEMA, false).unwrap();
+        let valid =
+            passport("pid:087499704 hgt:74in ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f");
+        assert!(validator.is_valid_passport(&valid));
+        for invalid in &[
+            "eyr:1972 cid:100 hcl:#18171d ecl:amb hgt:170 pid:186cm iyr:2018 byr:1926",
+            "hgt:59cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:3556412378 byr:2007",
+            "pid:087499704 hgt:194cm ecl:grn iyr:2012 eyr:2030 byr:1980 hcl:#623a2f",
+        ] {
+            assert!(!validator.is_valid_passport(&passport(invalid)));
+        }
+        assert_eq!(
+            validator.problems(&passport(
+                "hgt:200cm ecl:zzz eyr:2038 hcl:74454a iyr:2023 pid:355641237 byr:02002"
+            )),
+            vec![
+                "byr: '02002' is not a four digit year",
+                "ecl: 'zzz' is not one of amb, blu, brn, gry, grn, hzl, oth",
+                "eyr: 2038 above max 2030",
+                "hcl: '74454a' does not match ^(?:#[0-9a-f]{6})$",
+                "hgt: 200cm above max 193",
+                "iyr: 2023 above max 2020",
+            ]
+        );
+        assert_eq!(
+            validator.problems(&passport(
+                "hgt:70 ecl:amb eyr:2020 hcl:#74454a iyr:2010 byr:1920"
+            )),
+            vec![
+                "hgt: '70' has unit '', expected 'cm' or 'in'",
+                "pid: missing"
+            ]
+        );
+        let without_cid =
+            passport("ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 hgt:183cm");
+        assert!(validator.has_required_fields(&without_cid));
+    }
+
+    #[test]
+    fn json_schema() {
+        let schema = r#"{"fields": {
+            "size": {"type": "number", "units": {"": [1, 10], "kg": [1, 3]}},
+            "kind": {"type": "enum", "values": ["box", "bag"]},
+            "note": {"type": "any", "required": false}
+        }}"#;
+        let validator = parse_schema(schema, true).unwrap();
+        assert!(validator.is_valid_passport(&passport("size:7 kind:box")));
+        assert!(validator.is_valid_passport(&passport("size:3kg kind:bag note:fragile")));
+        assert!(!validator.is_valid_passport(&passport("size:4kg kind:bag")));
+        assert!(!validator.has_required_fields(&passport("size:4 note:x")));
+        assert!(parse_schema(
+            r#"{"fields": {"a": {"type": "regex", "pattern": "("}}}"#,
+            true
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn broken_rules_are_rejected() {
+        for broken in &[
+            "[fields.byr]\ntype = \"yaer\"\nmin = 1920\nmax = 2002\n",
+            "[fields.byr]\ntype = \"year\"\nmin = 1920\n",
+            "[fields.hcl]\ntype = \"regex\"\npatern = \"#[0-9a-f]{6}\"\n",
+            "[fields.hcl]\ntype = \"regex\"\npattern = \"#[0-9a-f]{6}\"\nrequird = false\n",
+            "[fields.cid]\nrequired = false\n",
+            "[field.cid]\ntype = \"any\"\n",
+        ] {
+            assert!(parse_schema(broken, false).is_err(), "{}", broken);
+        }
+    }
+
+    #[test]
+    fn strict_parsing() {
+        let validator = parse_schema(DEFAULT_SCHEMA, false).unwrap();
+        let words = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 hgt:183cm \
+                     ecl:blu hgt byr:19:37 cid:1 ecl:amb xyz:3";
+        let passport = Passport::parse(1, 2, words.split_whitespace());
+        assert_eq!(passport.fields["ecl"], "amb");
+        assert_eq!(
+            passport.parse_problems(&validator),
+            vec![
+                "'hgt': not a name:value pair",
+                "'byr:19:37': not a name:value pair",
+                "ecl: given more than once",
+                "xyz: unknown field",
+            ]
+        );
+        assert!(validator.is_valid_passport(&passport.fields));
+
+        let valid = "ecl:gry pid:860033327 eyr:2020 hcl:#fffffd byr:1937 iyr:2017 hgt:183cm";
+        let mut passports = vec![passport, Passport::parse(4, 4, valid.split_whitespace())];
+        assert_eq!(num_valid_passports_strict(&validator, &passports), 2);
+        drop_malformed(&validator, &mut passports);
+        assert_eq!(passports.len(), 1);
+        assert_eq!(passports[0].first_line, 4);
+        assert_eq!(num_valid_passports_strict(&validator, &passports), 1);
     }
 }
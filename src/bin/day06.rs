@@ -1,5 +1,7 @@
 use std::fs::File;
-use std::io::prelude::*;
+use std::io::{self, BufReader};
+
+use advent_of_code_2020::paragraphs::paragraphs;
 
 use bitvec::prelude::*;
 use gumdrop::Options;
@@ -16,21 +18,19 @@ impl Form {
 
 fn read_forms(filepath: &str) -> Vec<Vec<Form>> {
     match File::open(filepath) {
-        Ok(mut file) => {
-            let mut input = String::new();
-            if file.read_to_string(&mut input).is_ok() {
-                input
-                    .split("\n\n")
-                    .map(|group| {
-                        group
-                            .split_whitespace()
-                            .map(|line| Form::new(line.trim().bytes().collect()))
-                            .collect()
-                    })
-                    .collect()
-            } else {
+        Ok(file) => {
+            let groups: io::Result<Vec<Vec<Form>>> = paragraphs(BufReader::new(file))
+                .map(|group| {
+                    Ok(group?
+                        .words()
+                        .map(|answers| Form::new(answers.bytes().collect()))
+                        .collect())
+                })
+                .collect();
+            groups.unwrap_or_else(|error| {
+                println!("could not read file '{}': {}", filepath, error);
                 Vec::new()
-            }
+            })
         }
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
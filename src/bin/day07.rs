@@ -7,18 +7,14 @@ use combine::{
     sep_by1,
 };
 
+use advent_of_code_2020::bags::{validate, BagGraph, BagSpec, Rule};
 use gumdrop::Options;
 use std::collections::BTreeMap;
-use std::convert::TryInto;
 use std::fs::File;
 use std::io::Read;
+use std::str::FromStr;
 
-struct BagSpec {
-    number: usize,
-    bagtype: String,
-}
-
-fn parse_rules(filepath: &str) -> BTreeMap<String, Vec<BagSpec>> {
+fn parse_rules(filepath: &str) -> Vec<Rule> {
     let word = || many1::<String, _, _>(letter());
     let num = from_str(many1::<String, _, _>(digit()));
     let bagtype = || (word().skip(spaces()), word()).map(|(w1, w2)| format!("{} {}", w1, w2));
@@ -38,7 +34,7 @@ fn parse_rules(filepath: &str) -> BTreeMap<String, Vec<BagSpec>> {
     };
 
     let mut input = String::new();
-    let rules = || many1::<BTreeMap<String, Vec<BagSpec>>, _, _>(rule().skip(spaces()));
+    let rules = || many1::<Vec<Rule>, _, _>(rule().skip(spaces()));
 
     match File::open(filepath) {
         Ok(file) => {
@@ -48,105 +44,124 @@ fn parse_rules(filepath: &str) -> BTreeMap<String, Vec<BagSpec>> {
                     Ok((val, _)) => val,
                     Err(err) => {
                         println!("Parse error: {}", err);
-                        BTreeMap::new()
+                        Vec::new()
                     }
                 }
             } else {
                 println!("could not open file '{}'", filepath);
-                BTreeMap::new()
+                Vec::new()
             }
         }
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
-            BTreeMap::new()
+            Vec::new()
         }
     }
 }
 
 #[derive(Debug, Options)]
 struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
     #[options(free)]
     input_file: String,
+    #[options(help = "the bag to answer the questions for (default \"shiny gold\")")]
+    bag: Option<String>,
+    #[options(help = "print the rules as a graph instead of solving: dot or json")]
+    export: Option<ExportFormat>,
+    #[options(help = "with --export, only the bags that can end up inside this bag")]
+    from: Option<String>,
+    #[options(help = "list the ways the --bag bag ends up inside the --from bag")]
+    paths: bool,
 }
 
-fn num_bags_transitively_contain(
-    contained_bag: &str,
-    rules: &BTreeMap<String, Vec<BagSpec>>,
-) -> usize {
-    let mut num_bags = 0;
-    let mut cache = BTreeMap::new();
+#[derive(Debug)]
+enum ExportFormat {
+    Dot,
+    Json,
+}
 
-    // recursive function declared inside function to avoid using the same cache
-    // with different target bagtype
-    fn transitively_contains(
-        bag: &str,
-        contained_bag: &str,
-        rules: &BTreeMap<String, Vec<BagSpec>>,
-        cache: &mut BTreeMap<String, bool>,
-    ) -> bool {
-        if let Some(&result) = cache.get(bag) {
-            return result;
-        }
+impl FromStr for ExportFormat {
+    type Err = String;
 
-        if let Some(specs) = rules.get(bag) {
-            for spec in specs {
-                if spec.bagtype == contained_bag
-                    || transitively_contains(&spec.bagtype, contained_bag, rules, cache)
-                {
-                    cache.insert(bag.into(), true);
-                    return true;
-                }
-            }
-            cache.insert(bag.into(), false);
-            return false;
-        } else {
-            println!("No rule found for bag '{}'", bag);
-            return false;
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format {
+            "dot" => Ok(ExportFormat::Dot),
+            "json" => Ok(ExportFormat::Json),
+            _ => Err(format!(
+                "unknown export format '{}', expected dot or json",
+                format
+            )),
         }
     }
+}
 
-    for bag in rules.keys() {
-        if transitively_contains(bag, contained_bag, rules, &mut cache) {
-            num_bags += 1;
+fn print_paths(graph: &BagGraph, from: &str, to: &str) {
+    let paths = match graph.paths(from, to) {
+        Some(paths) => paths,
+        None => {
+            println!("no rules mention both a '{}' and a '{}' bag", from, to);
+            return;
         }
+    };
+    for path in &paths {
+        match path.multiplicity() {
+            Some(number) => println!("{} ({} bags)", path, number),
+            None => println!("{} (too many bags to count)", path),
+        }
+    }
+    println!("{} paths", paths.len());
+    if let Some(shortest) = graph.shortest_path(from, to) {
+        println!("shortest: {}", shortest);
+    }
+    if let Some(longest) = graph.longest_path(from, to) {
+        println!("longest: {}", longest);
     }
-    num_bags
 }
 
-fn number_contained_bags(bag: &str, rules: &BTreeMap<String, Vec<BagSpec>>) -> usize {
-    fn number_contained_bags_memoized(
-        bag: &str,
-        rules: &BTreeMap<String, Vec<BagSpec>>,
-        cache: &mut BTreeMap<String, usize>,
-    ) -> usize {
-        if let Some(&result) = cache.get(bag) {
-            return result;
+fn main() {
+    let opts = Arguments::parse_args_default_or_exit();
+    let rules = parse_rules(&opts.input_file);
+    if rules.is_empty() {
+        return;
+    }
+    let errors = validate(&rules);
+    if !errors.is_empty() {
+        for error in errors {
+            println!("{}", error);
         }
+        return;
+    }
 
-        let mut num_bags = 0;
-        if let Some(specs) = rules.get(bag) {
-            for spec in specs {
-                num_bags += spec.number * (1 + number_contained_bags(&spec.bagtype, rules));
-            }
-        } else {
-            println!("No rule found for bag '{}'", bag);
+    let rules: BTreeMap<String, Vec<BagSpec>> = rules.into_iter().collect();
+    let graph = BagGraph::new(&rules);
+    if let Some(format) = opts.export {
+        match graph.export(opts.from.as_deref()) {
+            Some(export) => match format {
+                ExportFormat::Dot => print!("{}", export.to_dot()),
+                ExportFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&export).unwrap())
+                }
+            },
+            None => println!("no rules mention a '{}' bag", opts.from.unwrap_or_default()),
         }
-        cache.insert(bag.into(), num_bags);
-        num_bags
+        return;
     }
 
-    let mut cache = BTreeMap::new();
-    return number_contained_bags_memoized(bag, rules, &mut cache);
-}
+    let bag = opts.bag.as_deref().unwrap_or("shiny gold");
+    if opts.paths {
+        match opts.from {
+            Some(from) => print_paths(&graph, &from, bag),
+            None => println!("--paths needs the outer bag as --from"),
+        }
+        return;
+    }
 
-fn main() {
-    let opts = Arguments::parse_args_default_or_exit();
-    let rules = parse_rules(&opts.input_file);
-    if !rules.is_empty() {
-        println!(
-            "Part 1: {}",
-            num_bags_transitively_contain("shiny gold", &rules)
-        );
-        println!("Part 2: {}", number_contained_bags("shiny gold", &rules));
+    match (graph.ancestors(bag), graph.total_contents(bag)) {
+        (Some(ancestors), Some(total)) => {
+            println!("Part 1: {}", ancestors.len());
+            println!("Part 2: {}", total);
+        }
+        _ => println!("no rules mention a '{}' bag", bag),
     }
 }
@@ -1,118 +1,183 @@
-use std::convert::TryInto;
-use std::fs::File;
-use std::io::prelude::*;
+use advent_of_code_2020::console::asm::{assemble, disassemble};
+use advent_of_code_2020::console::cfg::ControlFlowGraph;
+use advent_of_code_2020::console::debugger::{trace, Debugger};
+use advent_of_code_2020::console::{BootInstruction, Instruction, Machine, Outcome};
 
-use bitvec::prelude::*;
 use gumdrop::Options;
 
-enum Instruction {
-    Acc(isize),
-    Jmp(isize),
-    Nop(isize),
+/// The fix for a program that loops: which instruction to flip to what, and
+/// the accumulator when the fixed program halts
+#[derive(Debug, Eq, PartialEq)]
+struct Repair {
+    index: usize,
+    instruction: BootInstruction,
+    accumulator: isize,
 }
 
-impl Instruction {
-    fn parse(instr: &str) -> Option<Instruction> {
-        if instr.is_empty() {
-            return None
+/// Find the single `jmp`/`nop` flip that makes a looping program halt.
+///
+/// Instead of running the program once for every candidate, first mark all
+/// instructions from which the unmodified program halts, by walking the
+/// control flow backwards from the end. The fix is then the first
+/// instruction on the original looping path whose flipped successor is in
+/// that set, which makes this linear in the length of the program.
+fn find_broken_instruction(program: &[BootInstruction]) -> Option<Repair> {
+    let len = program.len() as isize;
+    let mut predecessors = vec![Vec::new(); program.len()];
+    let mut halts = vec![false; program.len()];
+    let mut queue = Vec::new();
+    for (index, instruction) in program.iter().enumerate() {
+        // a boot instruction has exactly one successor
+        match instruction.successors(index as isize)[0] {
+            Some(next) if next >= len => {
+                halts[index] = true;
+                queue.push(index);
+            }
+            Some(next) if next >= 0 => predecessors[next as usize].push(index),
+            // before the start or beyond the range of the pc, so it faults
+            _ => {}
+        }
+    }
+    while let Some(index) = queue.pop() {
+        for &predecessor in &predecessors[index] {
+            if !halts[predecessor] {
+                halts[predecessor] = true;
+                queue.push(predecessor);
+            }
         }
-        let mut part_iter = instr.split_whitespace();
-        if let (Some(op_string), Some(offset_string)) = (part_iter.next(), part_iter.next()) {
-            if let Ok(offset) = offset_string.trim_start_matches('+').parse() {
-                match op_string {
-                    "acc" => Some(Instruction::Acc(offset)),
-                    "jmp" => Some(Instruction::Jmp(offset)),
-                    "nop" => Some(Instruction::Nop(offset)),
-                    _ => {
-                        println!("Could not match operation '{}'", op_string);
-                        None
-                    }
+    }
+    if halts.first() == Some(&true) {
+        // nothing to repair
+        return None;
+    }
+
+    let mut visited = vec![false; program.len()];
+    let mut pc = 0;
+    while (0..len).contains(&pc) && !visited[pc as usize] {
+        let index = pc as usize;
+        visited[index] = true;
+        if let Some(flipped) = program[index].flipped() {
+            let next = flipped.successors(index as isize)[0];
+            if next.is_some_and(|next| next >= len || (next >= 0 && halts[next as usize])) {
+                let mut repaired = program.to_vec();
+                repaired[index] = flipped;
+                let mut machine = Machine::new(&repaired);
+                if machine.run() == Outcome::Halt {
+                    return Some(Repair {
+                        index,
+                        instruction: flipped,
+                        accumulator: machine.accumulator(),
+                    });
                 }
-            } else {
-                println!("Could not parse offset '{}'", offset_string);
-                None
             }
-        } else {
-            println!("Found unexpected number of words on line '{}'", instr);
-            None
+        }
+        match program[index].successors(index as isize)[0] {
+            Some(next) => pc = next,
+            None => break,
         }
     }
+    None
 }
 
-enum ProgramResult {
-    Success(isize),
-    Repeat(isize),
-    Error
+/// Run `program` with the instruction at `index` flipped, returning the
+/// accumulator if it halts. Leaves the program as it was.
+fn halts_with_flip(program: &mut [BootInstruction], index: usize) -> Option<isize> {
+    let flipped = program[index].flipped()?;
+    let original = std::mem::replace(&mut program[index], flipped);
+    let mut machine = Machine::new(program);
+    let result = if machine.run() == Outcome::Halt {
+        Some(machine.accumulator())
+    } else {
+        None
+    };
+    program[index] = original;
+    result
 }
 
-fn accumulator_before_instruction_repeat(program: &[Instruction]) -> ProgramResult {
-    let mut accumulator = 0;
-    let mut cur_instruction: isize = 0;
-    let mut instruction_executed = bitvec![0; program.len()];
-    loop {
-        if let Ok(index) = TryInto::<usize>::try_into(cur_instruction) {
-            if index >= program.len() {
-                return ProgramResult::Success(accumulator);
-            }
+/// Every single `jmp`/`nop` flip that makes the program halt
+fn all_repairs(program: &[BootInstruction]) -> Vec<Repair> {
+    let mut program = program.to_vec();
+    (0..program.len())
+        .filter_map(|index| {
+            let accumulator = halts_with_flip(&mut program, index)?;
+            Some(Repair {
+                index,
+                instruction: program[index].flipped()?,
+                accumulator,
+            })
+        })
+        .collect()
+}
 
-            if let Some(true) = instruction_executed.get(index) {
-                return ProgramResult::Repeat(accumulator);
-            }
+/// Every pair of flips that makes the program halt where neither flip does
+/// on its own. One of them has to be on the path the unmodified program
+/// takes, otherwise nothing it executes changes.
+fn all_repair_pairs(program: &[BootInstruction]) -> Vec<(usize, usize, isize)> {
+    let mut program = program.to_vec();
+    let mut on_path = vec![false; program.len()];
+    let mut machine = Machine::new(&program);
+    while !machine.at_repeat() && machine.current().is_some() {
+        on_path[machine.pc() as usize] = true;
+        machine.step();
+    }
 
-            match program[index] {
-                Instruction::Acc(value) => {
-                    accumulator += value;
-                    cur_instruction += 1;
-                }
-                Instruction::Jmp(value) => {
-                    cur_instruction += value;
-                }
-                Instruction::Nop(_) => cur_instruction += 1,
+    let fixes_alone: Vec<bool> = (0..program.len())
+        .map(|index| halts_with_flip(&mut program, index).is_some())
+        .collect();
+    let candidates: Vec<usize> = (0..program.len())
+        .filter(|&index| program[index].flipped().is_some() && !fixes_alone[index])
+        .collect();
+
+    let mut pairs = Vec::new();
+    for (i, &first) in candidates.iter().enumerate() {
+        for &second in &candidates[i + 1..] {
+            if !on_path[first] && !on_path[second] {
+                continue;
             }
-            instruction_executed.set(index, true);
-        } else {
-            println!(
-                "Instruction pointer overflow or underflow: {}",
-                cur_instruction
-            );
-            return ProgramResult::Error;
+            let flipped = program[first].flipped().unwrap();
+            let original = std::mem::replace(&mut program[first], flipped);
+            if let Some(accumulator) = halts_with_flip(&mut program, second) {
+                pairs.push((first, second, accumulator));
+            }
+            program[first] = original;
         }
     }
+    pairs
 }
 
-fn find_broken_instruction(program: &mut [Instruction]) -> Option<isize> {
-    for i in 0..program.len() {
-        match program[i] {
-            Instruction::Jmp(value) => {
-                program[i] = Instruction::Nop(value);
-                if let ProgramResult::Success(value) = accumulator_before_instruction_repeat(program) {
-                    return Some(value);
-                }
-                program[i] = Instruction::Jmp(value);
-            }
-            Instruction::Nop(value) => {
-                program[i] = Instruction::Jmp(value);
-                if let ProgramResult::Success(value) = accumulator_before_instruction_repeat(program) {
-                    return Some(value);
-                }
-                program[i] = Instruction::Nop(value);
-            }
-            _ => {}
+fn print_repairs(program: &[BootInstruction], pairs: bool) {
+    let repairs = all_repairs(program);
+    for repair in &repairs {
+        println!(
+            "instruction {}: '{}' -> '{}', accumulator {}",
+            repair.index, program[repair.index], repair.instruction, repair.accumulator
+        );
+    }
+    println!("{} single instruction fixes", repairs.len());
+
+    if pairs {
+        let pairs = all_repair_pairs(program);
+        for (first, second, accumulator) in &pairs {
+            println!(
+                "instructions {} and {}: '{}', '{}', accumulator {}",
+                first, second, program[*first], program[*second], accumulator
+            );
         }
+        println!("{} pairs of fixes", pairs.len());
     }
-    None
 }
 
-fn read_program(filepath: &str) -> Vec<Instruction> {
-    match File::open(filepath) {
-        Ok(mut file) => {
-            let mut input = String::new();
-            if file.read_to_string(&mut input).is_ok() {
-                input.split("\n").filter_map(Instruction::parse).collect()
-            } else {
+fn read_program(filepath: &str) -> Vec<BootInstruction> {
+    match std::fs::read_to_string(filepath) {
+        Ok(input) => match assemble(&input) {
+            Ok(program) => program,
+            Err(errors) => {
+                for error in errors {
+                    println!("{}: {}", filepath, error);
+                }
                 Vec::new()
             }
-        }
+        },
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
             Vec::new()
@@ -122,23 +187,149 @@ fn read_program(filepath: &str) -> Vec<Instruction> {
 
 #[derive(Debug, Options)]
 struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
     #[options(free)]
     input_file: String,
+    #[options(help = "print pc, instruction and accumulator for every step of part 1")]
+    trace: bool,
+    #[options(help = "step through the program in an interactive debugger")]
+    debug: bool,
+    #[options(help = "swap jmp and nop at this index before debugging")]
+    flip: Option<usize>,
+    #[options(help = "print basic blocks, dead code and infinite loops")]
+    cfg: bool,
+    #[options(help = "write the control-flow graph as graphviz dot to a file")]
+    dot: Option<String>,
+    #[options(help = "print the program in canonical form")]
+    disassemble: bool,
+    #[options(help = "list every jmp/nop flip that makes the program halt")]
+    all_fixes: bool,
+    #[options(help = "with --all-fixes, also list pairs of flips that only work together")]
+    pairs: bool,
+}
+
+fn print_cfg(cfg: &ControlFlowGraph) {
+    println!("{} basic blocks", cfg.blocks().len());
+    for (index, block) in cfg.blocks().iter().enumerate() {
+        println!(
+            "block {}: instructions {}..{} -> {:?}",
+            index, block.instructions.start, block.instructions.end, block.successors
+        );
+    }
+    for range in cfg.dead_code() {
+        println!("dead code: instructions {}..{}", range.start, range.end);
+    }
+    let loops = cfg.infinite_loops();
+    if !loops.is_empty() {
+        println!("blocks that loop forever: {:?}", loops);
+    }
 }
 
 fn main() {
     let opts = Arguments::parse_args_default_or_exit();
-    let mut program = read_program(&opts.input_file);
+    let program = read_program(&opts.input_file);
+    if opts.disassemble {
+        print!("{}", disassemble(&program));
+        return;
+    }
+    if opts.debug {
+        let mut program = program;
+        if let Some(index) = opts.flip {
+            match program
+                .get(index)
+                .and_then(|instruction| instruction.flipped())
+            {
+                Some(flipped) => program[index] = flipped,
+                None => {
+                    println!("no jmp or nop at index {}", index);
+                    return;
+                }
+            }
+        }
+        let stdin = std::io::stdin();
+        let mut debugger = Debugger::new(Machine::new(&program));
+        if let Err(error) = debugger.repl(stdin.lock(), &mut std::io::stdout()) {
+            println!("debugger failed: {}", error);
+        }
+        return;
+    }
+    if opts.cfg || opts.dot.is_some() {
+        let cfg = ControlFlowGraph::new(&program);
+        if opts.cfg {
+            print_cfg(&cfg);
+        }
+        if let Some(path) = &opts.dot {
+            if let Err(error) = std::fs::write(path, cfg.to_dot(&program)) {
+                println!("could not write '{}': {}", path, error);
+            }
+        }
+    }
     if !program.is_empty() {
-        if let ProgramResult::Repeat(value) = accumulator_before_instruction_repeat(&program) {
-            println!("Part 1: {}", value);
+        let mut machine = Machine::new(&program);
+        let outcome = if opts.trace {
+            trace(&mut machine, &mut std::io::stdout()).unwrap_or_else(|error| {
+                println!("could not write trace: {}", error);
+                machine.run()
+            })
         } else {
-            println!("Part 1: No result found");
+            machine.run()
+        };
+        match outcome {
+            Outcome::Loop => println!("Part 1: {}", machine.accumulator()),
+            Outcome::Fault(fault) => println!("Part 1: {}", fault),
+            Outcome::Halt => println!("Part 1: No result found"),
         }
-        if let Some(value) = find_broken_instruction(&mut program) {
-            println!("Part 2: {}", value);
+        if opts.all_fixes {
+            print_repairs(&program, opts.pairs);
+        } else if let Some(repair) = find_broken_instruction(&program) {
+            println!(
+                "Part 2: {} (instruction {} changed to '{}')",
+                repair.accumulator, repair.index, repair.instruction
+            );
         } else {
             println!("Part 2: No result found");
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use advent_of_code_2020::console::EXAMPLE;
+
+    #[test]
+    fn repair_pairs() {
+        // flipping instruction 1 halts, so does flipping both 0 and 5
+        let program = assemble("nop +4\njmp +0\nacc +1\njmp +3\nnop +0\njmp -5").unwrap();
+        let indices: Vec<_> = all_repairs(&program).iter().map(|r| r.index).collect();
+        assert_eq!(indices, vec![1]);
+        assert_eq!(all_repair_pairs(&program), vec![(0, 5, 0)]);
+    }
+
+    #[test]
+    fn example_repair() {
+        let program = assemble(EXAMPLE).unwrap();
+        assert_eq!(
+            find_broken_instruction(&program),
+            Some(Repair {
+                index: 7,
+                instruction: BootInstruction::Nop(-4),
+                accumulator: 8,
+            })
+        );
+    }
+
+    #[test]
+    fn overflowing_jump() {
+        let program = assemble("nop +0\njmp +9223372036854775807").unwrap();
+        assert_eq!(
+            find_broken_instruction(&program),
+            Some(Repair {
+                index: 1,
+                instruction: BootInstruction::Nop(isize::MAX),
+                accumulator: 0,
+            })
+        );
+    }
+}
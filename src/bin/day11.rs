@@ -1,8 +1,13 @@
+use advent_of_code_2020::render::animate;
+use advent_of_code_2020::viewer;
 use advent_of_code_2020::Mat;
 use std::fs::File;
 use std::io::prelude::*;
+use std::path::Path;
 
+use anyhow::Result;
 use gumdrop::Options;
+use serde::Deserialize;
 
 #[derive(Clone, Copy, Eq, PartialEq)]
 enum Tile {
@@ -12,42 +17,172 @@ enum Tile {
 }
 
 impl Tile {
-    fn from_char(c: char) -> Option<Tile> {
-        match c {
-            'L' => Some(Tile::EmptySeat),
-            '#' => Some(Tile::OccupiedSeat),
-            '.' => Some(Tile::Floor),
-            _ => None,
+    fn from_char(c: char, glyphs: &Glyphs) -> Option<Tile> {
+        if c == glyphs.empty {
+            Some(Tile::EmptySeat)
+        } else if c == glyphs.occupied {
+            Some(Tile::OccupiedSeat)
+        } else if c == glyphs.floor {
+            Some(Tile::Floor)
+        } else {
+            None
+        }
+    }
+
+    fn to_char(self, glyphs: &Glyphs) -> char {
+        match self {
+            Tile::EmptySeat => glyphs.empty,
+            Tile::OccupiedSeat => glyphs.occupied,
+            Tile::Floor => glyphs.floor,
         }
     }
 }
 type Map = Mat<Tile>;
 
-fn read_map(filepath: &str) -> Option<Map> {
+/// Which seats a seat looks at when deciding whether to change
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "lowercase")]
+enum Visibility {
+    /// the eight directly surrounding tiles
+    Adjacent,
+    /// the first seat visible in each of the eight directions
+    Line,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Glyphs {
+    empty: char,
+    occupied: char,
+    floor: char,
+}
+
+impl Default for Glyphs {
+    fn default() -> Self {
+        Glyphs {
+            empty: 'L',
+            occupied: '#',
+            floor: '.',
+        }
+    }
+}
+
+/// Seating rules, read from a toml file such as:
+///
+/// ```toml
+/// threshold = 5
+/// visibility = "line"
+/// max_distance = 3
+///
+/// [glyphs]
+/// empty = "L"
+/// occupied = "#"
+/// floor = "."
+/// ```
+///
+/// `max_distance` only applies to line visibility and defaults to unlimited,
+/// the glyphs default to the ones used by the puzzle.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rules {
+    threshold: usize,
+    visibility: Visibility,
+    max_distance: Option<usize>,
+    #[serde(default)]
+    glyphs: Glyphs,
+}
+
+impl Rules {
+    fn part1() -> Rules {
+        Rules {
+            threshold: 4,
+            visibility: Visibility::Adjacent,
+            max_distance: None,
+            glyphs: Glyphs::default(),
+        }
+    }
+
+    fn part2() -> Rules {
+        Rules {
+            threshold: 5,
+            visibility: Visibility::Line,
+            max_distance: None,
+            glyphs: Glyphs::default(),
+        }
+    }
+
+    /// how many tiles a seat can look in each direction, `None` if unlimited
+    fn reach(&self) -> Option<usize> {
+        match self.visibility {
+            Visibility::Adjacent => Some(1),
+            Visibility::Line => self.max_distance,
+        }
+    }
+}
+
+fn parse_rules(input: &str) -> Result<Rules, String> {
+    let rules: Rules = toml::from_str(input).map_err(|error| error.to_string())?;
+    let glyphs = &rules.glyphs;
+    if glyphs.empty == glyphs.occupied
+        || glyphs.empty == glyphs.floor
+        || glyphs.occupied == glyphs.floor
+    {
+        return Err(format!(
+            "the glyphs for empty seats ('{}'), occupied seats ('{}') and floor ('{}') must differ",
+            glyphs.empty, glyphs.occupied, glyphs.floor
+        ));
+    }
+    Ok(rules)
+}
+
+fn read_rules(filepath: &str) -> Option<Rules> {
+    match std::fs::read_to_string(filepath) {
+        Ok(input) => match parse_rules(&input) {
+            Ok(rules) => Some(rules),
+            Err(error) => {
+                println!("could not parse rules '{}': {}", filepath, error);
+                None
+            }
+        },
+        Err(error) => {
+            println!("could not open file '{}': {}", filepath, error);
+            None
+        }
+    }
+}
+
+fn parse_map(input: &str, glyphs: &Glyphs) -> Option<Map> {
+    let v: Vec<Vec<Tile>> = input
+        .lines()
+        .map(|line| {
+            line.chars()
+                .flat_map(|c| Tile::from_char(c, glyphs))
+                .collect()
+        })
+        .collect();
+
+    if !v.is_empty() && !v[0].is_empty() && v.iter().all(|row| row.len() == v[0].len()) {
+        let mut map = Mat::new(v[0].len() + 2, v.len() + 2, Tile::Floor);
+        for (i, row) in v.iter().enumerate() {
+            for (j, tile) in row.iter().enumerate() {
+                map[(j + 1, i + 1)] = *tile;
+            }
+        }
+        Some(map)
+    } else {
+        None
+    }
+}
+
+fn read_map(filepath: &str, glyphs: &Glyphs) -> Option<Map> {
     match File::open(filepath) {
-        Ok(file) => {
-            let lines: Option<Vec<Vec<Tile>>> = std::io::BufReader::new(file)
-                .lines()
-                .map(|line| {
-                    line.ok()
-                        .map(|s| s.chars().flat_map(|c| Tile::from_char(c)).collect())
-                })
-                .collect();
-
-            lines.and_then(|v| {
-                if !v.is_empty() && !v[0].is_empty() && v.iter().all(|row| row.len() == v[0].len())
-                {
-                    let mut map = Mat::new(v[0].len() + 2, v.len() + 2, Tile::Floor);
-                    for (i, row) in v.iter().enumerate() {
-                        for (j, tile) in row.iter().enumerate() {
-                            map[(j + 1, i + 1)] = *tile;
-                        }
-                    }
-                    Some(map)
-                } else {
-                    None
-                }
-            })
+        Ok(mut file) => {
+            let mut input = String::new();
+            if file.read_to_string(&mut input).is_ok() {
+                parse_map(&input, glyphs)
+            } else {
+                None
+            }
         }
         Err(error) => {
             println!("could not open file '{}': {}", filepath, error);
@@ -67,95 +202,414 @@ static DIRECTIONS: &[(isize, isize)] = &[
     (-1, -1),
 ];
 
-fn count_occupied_around(map: &Map, row: usize, column: usize) -> usize {
-    DIRECTIONS
-        .iter()
-        .map(|&(hstep, vstep)| {
-            map[(
-                (column as isize + hstep) as usize,
-                (row as isize + vstep) as usize,
-            )]
-        })
-        .filter(|&t| t == Tile::OccupiedSeat)
-        .count()
+/// Position of the first seat seen from `(column, row)` in the given direction,
+/// looking at most `reach` tiles far.
+fn first_seat_in_direction(
+    map: &Map,
+    column: usize,
+    row: usize,
+    (hstep, vstep): (isize, isize),
+    reach: Option<usize>,
+) -> Option<(usize, usize)> {
+    let mut c = column as isize;
+    let mut r = row as isize;
+    let mut distance = 0;
+    loop {
+        c += hstep;
+        r += vstep;
+        distance += 1;
+
+        if c < 0 || c >= map.width() as isize || r < 0 || r >= map.height() as isize {
+            return None;
+        }
+        if reach.is_some_and(|reach| distance > reach) {
+            return None;
+        }
+        if map[(c as usize, r as usize)] != Tile::Floor {
+            return Some((c as usize, r as usize));
+        }
+    }
 }
 
-fn count_first_occupied_directions(map: &Map, row: usize, column: usize) -> usize {
-    let mut result = 0;
-    for &(hstep, vstep) in DIRECTIONS {
-        let mut c = column as isize;
-        let mut r = row as isize;
-        loop {
-            c += hstep;
-            r += vstep;
+/// The seats of a map together with the seats each of them looks at.
+///
+/// Floor never changes, so only seats are tracked. The state of the two most
+/// recent generations is kept, indexed by generation parity, together with the
+/// number of occupied neighbours of every seat in each of them.
+///
+/// A seat's next state only depends on its own state and that of its
+/// neighbours, so if none of those differ from two generations ago, its next
+/// state is the one it had two generations ago, which is still in the buffer.
+/// A step therefore only re-examines the frontier: seats that differ from two
+/// generations ago and their neighbours. Regions blinking in lock-step, which
+/// is how most of a large map spends the time until the arrangement around it
+/// settles, cost nothing.
+struct Seating {
+    positions: Vec<(usize, usize)>,
+    // neighbours of seat i are neighbours[neighbour_start[i]..neighbour_start[i + 1]]
+    neighbour_start: Vec<usize>,
+    neighbours: Vec<u32>,
+    occupied: [Vec<bool>; 2],
+    occupied_neighbours: [Vec<u8>; 2],
+    // number of seats whose state differs between the two generations
+    num_differing: usize,
+    frontier: Vec<u32>,
+    changed: Vec<u32>,
+    last_queued: Vec<usize>,
+    generation: usize,
+    threshold: usize,
+}
 
-            if c < 0 || c >= map.width() as isize || r < 0 || r >= map.height() as isize {
-                break;
-            }
-            let cur_tile = map[(c as usize, r as usize)];
-            if cur_tile != Tile::Floor {
-                if cur_tile == Tile::OccupiedSeat {
-                    result += 1;
+impl Seating {
+    fn new(map: &Map, rules: &Rules) -> Seating {
+        let mut index = Mat::new(map.width(), map.height(), u32::MAX);
+        let mut positions = Vec::new();
+        for column in 0..map.width() {
+            for row in 0..map.height() {
+                if map[(column, row)] != Tile::Floor {
+                    index[(column, row)] = positions.len() as u32;
+                    positions.push((column, row));
                 }
-                break;
             }
         }
+
+        // seeing is symmetric: if a sees b as the first seat in some direction,
+        // b sees a in the opposite one, so these are also the seats looking at a
+        let reach = rules.reach();
+        let mut neighbour_start = vec![0];
+        let mut neighbours = Vec::new();
+        for &(column, row) in &positions {
+            neighbours.extend(
+                DIRECTIONS
+                    .iter()
+                    .filter_map(|&direction| {
+                        first_seat_in_direction(map, column, row, direction, reach)
+                    })
+                    .map(|position| index[position]),
+            );
+            neighbour_start.push(neighbours.len());
+        }
+
+        let occupied: Vec<bool> = positions
+            .iter()
+            .map(|&position| map[position] == Tile::OccupiedSeat)
+            .collect();
+        let occupied_neighbours: Vec<u8> = neighbour_start
+            .windows(2)
+            .map(|range| {
+                neighbours[range[0]..range[1]]
+                    .iter()
+                    .filter(|&&seat| occupied[seat as usize])
+                    .count() as u8
+            })
+            .collect();
+
+        let num_seats = positions.len();
+        Seating {
+            positions,
+            neighbour_start,
+            neighbours,
+            occupied: [occupied.clone(), occupied],
+            occupied_neighbours: [occupied_neighbours.clone(), occupied_neighbours],
+            num_differing: 0,
+            frontier: (0..num_seats as u32).collect(),
+            changed: Vec::new(),
+            last_queued: vec![0; num_seats],
+            generation: 0,
+            threshold: rules.threshold,
+        }
     }
-    result
-}
 
-type CountFun = fn(&Map, usize, usize) -> usize;
+    /// Advance one generation
+    fn step(&mut self) -> Progress {
+        let current = self.generation % 2;
+        let next = 1 - current;
+        self.generation += 1;
 
-fn step_map(map: &Map, occupied_swap_threshold: usize, countfun: CountFun) -> Map {
-    let mut new_map = Map::new(map.width(), map.height(), Tile::Floor);
-    for i in 1..map.width() - 1 {
-        for j in 1..map.height() - 1 {
-            new_map[(i, j)] = match map[(i, j)] {
-                Tile::EmptySeat if countfun(map, j, i) == 0 => Tile::OccupiedSeat,
-                Tile::OccupiedSeat if countfun(map, j, i) >= occupied_swap_threshold => Tile::EmptySeat,
-                tile => tile,
+        self.changed.clear();
+        for &seat in &self.frontier {
+            let seat = seat as usize;
+            let count = self.occupied_neighbours[current][seat] as usize;
+            let occupied = if self.occupied[current][seat] {
+                count < self.threshold
+            } else {
+                count == 0
+            };
+            if occupied != self.occupied[next][seat] {
+                self.changed.push(seat as u32);
             }
         }
+
+        self.frontier.clear();
+        for &seat in &self.changed {
+            let seat = seat as usize;
+            let now_occupied = !self.occupied[next][seat];
+            self.occupied[next][seat] = now_occupied;
+            if now_occupied == self.occupied[current][seat] {
+                self.num_differing -= 1;
+            } else {
+                self.num_differing += 1;
+            }
+
+            let neighbours =
+                &self.neighbours[self.neighbour_start[seat]..self.neighbour_start[seat + 1]];
+            for &neighbour in neighbours {
+                let count = &mut self.occupied_neighbours[next][neighbour as usize];
+                if now_occupied {
+                    *count += 1;
+                } else {
+                    *count -= 1;
+                }
+            }
+            for &queued in std::iter::once(&(seat as u32)).chain(neighbours) {
+                if self.last_queued[queued as usize] != self.generation {
+                    self.last_queued[queued as usize] = self.generation;
+                    self.frontier.push(queued);
+                }
+            }
+        }
+
+        if self.generation == 1 {
+            // the buffer for generation -1 was filled with generation 0, so the
+            // comparison above is not against a real predecessor yet
+            self.frontier = (0..self.positions.len() as u32).collect();
+        }
+
+        if self.num_differing == 0 {
+            Progress::Stable
+        } else if self.changed.is_empty() {
+            // nothing differs from two generations ago, yet the last two differ
+            Progress::Oscillating
+        } else {
+            Progress::Changed
+        }
+    }
+
+    fn num_occupied(&self) -> usize {
+        self.occupied[self.generation % 2]
+            .iter()
+            .filter(|&&occupied| occupied)
+            .count()
+    }
+
+    /// Write the current state of the seats into a copy of `map`
+    fn to_map(&self, map: &Map) -> Map {
+        let mut result = map.clone();
+        for (&position, &occupied) in self
+            .positions
+            .iter()
+            .zip(&self.occupied[self.generation % 2])
+        {
+            result[position] = if occupied {
+                Tile::OccupiedSeat
+            } else {
+                Tile::EmptySeat
+            };
+        }
+        result
     }
-    new_map
 }
 
-fn fixed_point(map: &Map, occupied_swap_threshold: usize, countfun: CountFun) -> Map {
-    let mut map = map.clone();
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Progress {
+    Changed,
+    Stable,
+    Oscillating,
+}
+
+/// Run the seating rules until nothing changes, `None` if the seats end up
+/// alternating between two states instead
+fn fixed_point(map: &Map, rules: &Rules) -> Option<Seating> {
+    let mut seating = Seating::new(map, rules);
     loop {
-        let new_map = step_map(&map, occupied_swap_threshold, countfun);
-        if new_map == map {
-            return map;
+        match seating.step() {
+            Progress::Changed => {}
+            Progress::Stable => return Some(seating),
+            Progress::Oscillating => return None,
         }
-        map = new_map;
+    }
+}
+
+fn tile_colour(tile: &Tile) -> [u8; 3] {
+    match tile {
+        Tile::EmptySeat => [90, 160, 90],
+        Tile::OccupiedSeat => [220, 90, 40],
+        Tile::Floor => [30, 30, 30],
+    }
+}
+
+/// Every generation up to the fixed point, or until the seats start alternating
+fn generations(map: &Map, rules: &Rules) -> Vec<Map> {
+    let mut seating = Seating::new(map, rules);
+    let mut generations = vec![seating.to_map(map)];
+    while seating.step() == Progress::Changed {
+        generations.push(seating.to_map(map));
+    }
+    generations
+}
+
+fn print_occupied(label: &str, seating: Option<Seating>) {
+    if let Some(seating) = seating {
+        println!("{}: {}", label, seating.num_occupied());
+    } else {
+        println!("{}: seats never settle", label);
     }
 }
 
 #[derive(Debug, Options)]
 struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
     #[options(free)]
     input_file: String,
+    #[options(help = "toml file with custom seating rules")]
+    rules: Option<String>,
+    #[options(help = "directory to write an image of every generation to")]
+    animate: Option<String>,
+    #[options(help = "step through the generations of the given part in the terminal")]
+    view: Option<usize>,
+}
+
+fn run(map: &Map, rules: &Rules, label: &str, opts: &Arguments, part: usize) {
+    print_occupied(label, fixed_point(map, rules));
+    let view = opts.view == Some(part);
+    if opts.animate.is_none() && !view {
+        return;
+    }
+
+    let generations = generations(map, rules);
+    if let Some(directory) = &opts.animate {
+        let prefix = format!("part{}_", part);
+        if let Err(error) = animate(&generations, Path::new(directory), &prefix, 4, tile_colour) {
+            println!("could not write animation to '{}': {}", directory, error);
+        }
+    }
+    if view {
+        if let Err(error) = viewer::view(&generations, |tile| tile.to_char(&rules.glyphs)) {
+            println!("could not show generations: {}", error);
+        }
+    }
 }
 
 fn main() {
     let opts = Arguments::parse_args_default_or_exit();
-    let map_opt = read_map(&opts.input_file);
+    // custom rules run a single part
+    let parts = if opts.rules.is_some() { 1 } else { 2 };
+    if let Some(part) = opts.view.filter(|&part| part == 0 || part > parts) {
+        println!("there is no part {}", part);
+        return;
+    }
+    if let Some(rules_file) = &opts.rules {
+        if let Some(rules) = read_rules(rules_file) {
+            if let Some(map) = read_map(&opts.input_file, &rules.glyphs) {
+                run(&map, &rules, "Occupied", &opts, 1);
+            } else {
+                println!("Something went wrong while reading the map");
+            }
+        }
+        return;
+    }
+
+    let map_opt = read_map(&opts.input_file, &Glyphs::default());
     if let Some(map) = map_opt {
-        println!(
-            "Part 1: {}",
-            fixed_point(&map, 4, count_occupied_around)
-                .iter_elements()
-                .filter(|&&tile| tile == Tile::OccupiedSeat)
-                .count()
-        );
-        println!(
-            "Part 2: {}",
-            fixed_point(&map, 5, count_first_occupied_directions)
-                .iter_elements()
-                .filter(|&&tile| tile == Tile::OccupiedSeat)
-                .count()
-        );
+        run(&map, &Rules::part1(), "Part 1", &opts, 1);
+        run(&map, &Rules::part2(), "Part 2", &opts, 2);
     } else {
         println!("Something went wrong while reading the map");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    static EXAMPLE: &str = "L.LL.LL.LL
+LLLLLLL.LL
+L.L.L..L..
+LLLL.LL.LL
+L.LL.LL.LL
+L.LLLLL.LL
+..L.L.....
+LLLLLLLLLL
+L.LLLLLL.L
+L.LLLLL.LL
+";
+
+    #[test]
+    fn example_part1() {
+        let map = parse_map(EXAMPLE, &Glyphs::default()).unwrap();
+        assert_eq!(
+            fixed_point(&map, &Rules::part1()).unwrap().num_occupied(),
+            37
+        );
+    }
+
+    #[test]
+    fn example_part2() {
+        let map = parse_map(EXAMPLE, &Glyphs::default()).unwrap();
+        assert_eq!(
+            fixed_point(&map, &Rules::part2()).unwrap().num_occupied(),
+            26
+        );
+    }
+
+    #[test]
+    fn rules_file() {
+        let rules = parse_rules("threshold = 5\nvisibility = \"line\"\n").unwrap();
+        assert_eq!(rules.glyphs.empty, 'L');
+        assert_eq!(rules.glyphs.occupied, '#');
+        assert_eq!(rules.glyphs.floor, '.');
+        let map = parse_map(EXAMPLE, &rules.glyphs).unwrap();
+        assert_eq!(fixed_point(&map, &rules).unwrap().num_occupied(), 26);
+
+        let near = parse_rules("threshold = 5\nvisibility = \"line\"\nmax_distance = 1\n").unwrap();
+        assert_eq!(near.reach(), Some(1));
+        // looking one seat away is looking at the adjacent seats
+        assert_eq!(fixed_point(&map, &near).unwrap().num_occupied(), 46);
+        let adjacent = parse_rules("threshold = 5\nvisibility = \"adjacent\"\n").unwrap();
+        assert_eq!(fixed_point(&map, &adjacent).unwrap().num_occupied(), 46);
+    }
+
+    #[test]
+    fn custom_glyphs() {
+        let rules = parse_rules(
+            "threshold = 4\nvisibility = \"adjacent\"\n[glyphs]\nempty = \"o\"\noccupied = \"x\"\nfloor = \" \"\n",
+        )
+        .unwrap();
+        let custom: String = EXAMPLE
+            .chars()
+            .map(|c| match c {
+                'L' => 'o',
+                '.' => ' ',
+                c => c,
+            })
+            .collect();
+        let map = parse_map(&custom, &rules.glyphs).unwrap();
+        assert_eq!(fixed_point(&map, &rules).unwrap().num_occupied(), 37);
+        assert!(parse_map(EXAMPLE, &rules.glyphs).is_none());
+
+        let duplicate = "threshold = 4\nvisibility = \"adjacent\"\n[glyphs]\nempty = \"#\"\n";
+        assert!(parse_rules(duplicate).is_err());
+    }
+
+    #[test]
+    fn misspelled_keys() {
+        let misspelled = [
+            "threshold = 5\nvisibility = \"line\"\nmax_distnace = 1\n",
+            "threshold = 5\nvisibility = \"line\"\n[glyphs]\nemtpy = \"x\"\n",
+        ];
+        for rules in &misspelled {
+            assert!(parse_rules(rules).is_err(), "{}", rules);
+        }
+    }
+
+    #[test]
+    fn oscillation_is_detected() {
+        let map = parse_map("LL", &Glyphs::default()).unwrap();
+        let rules = Rules {
+            threshold: 1,
+            ..Rules::part1()
+        };
+        assert!(fixed_point(&map, &rules).is_none());
+    }
+}
@@ -1,3 +1,9 @@
+use advent_of_code_2020::render::{animate, contact_sheet};
+use advent_of_code_2020::viewer;
+use advent_of_code_2020::Mat;
+use std::path::Path;
+
+use gumdrop::Options;
 use ndarray::prelude::*;
 
 fn step(current: &Array<u8, Ix3>, dest: &mut Array<u8, Ix3>) {
@@ -22,7 +28,8 @@ fn step(current: &Array<u8, Ix3>, dest: &mut Array<u8, Ix3>) {
     }
 }
 
-fn simulate(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Array<u8, Ix3> {
+/// All generations, starting with the initial one
+fn simulate(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Vec<Array<u8, Ix3>> {
     let size_increase = (n_steps + 1) * 2; // add 2 for to index around the edges
     let height = start_slice.shape()[0];
     let width = start_slice.shape()[1];
@@ -39,6 +46,7 @@ fn simulate(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Array<u8, Ix3> {
             n_steps + 1..n_steps + 1 + width
         ])
         .assign(&start_slice.view());
+    let mut generations = vec![start.clone()];
     let mut dest = Array::<u8, Ix3>::zeros((
         depth + size_increase,
         height + size_increase,
@@ -47,8 +55,9 @@ fn simulate(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Array<u8, Ix3> {
     for _ in 0..n_steps {
         step(&start, &mut dest);
         std::mem::swap(&mut start, &mut dest);
+        generations.push(start.clone());
     }
-    start
+    generations
 }
 
 fn step2(current: &Array<u8, Ix4>, dest: &mut Array<u8, Ix4>) {
@@ -76,7 +85,8 @@ fn step2(current: &Array<u8, Ix4>, dest: &mut Array<u8, Ix4>) {
     }
 }
 
-fn simulate2(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Array<u8, Ix4> {
+/// All generations, starting with the initial one
+fn simulate2(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Vec<Array<u8, Ix4>> {
     let size_increase = (n_steps + 1) * 2; // add 2 for to index around the edges
     let height = start_slice.shape()[0];
     let width = start_slice.shape()[1];
@@ -96,6 +106,7 @@ fn simulate2(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Array<u8, Ix4> {
             n_steps + 1..n_steps + 1 + width
         ])
         .assign(&start_slice.view());
+    let mut generations = vec![start.clone()];
     let mut dest = Array::<u8, Ix4>::zeros((
         hyper + size_increase,
         depth + size_increase,
@@ -105,11 +116,69 @@ fn simulate2(start_slice: &Array<u8, Ix2>, n_steps: usize) -> Array<u8, Ix4> {
     for _ in 0..n_steps {
         step2(&start, &mut dest);
         std::mem::swap(&mut start, &mut dest);
+        generations.push(start.clone());
     }
-    start
+    generations
+}
+
+const GAP: u8 = 2;
+
+fn slice_to_mat(slice: ArrayView2<u8>) -> Mat<u8> {
+    let mut mat = Mat::new(slice.shape()[1], slice.shape()[0], 0);
+    for ((y, x), &value) in slice.indexed_iter() {
+        mat[(x, y)] = value;
+    }
+    mat
+}
+
+/// The z layers of a 3d generation side by side
+fn layer_sheet(generation: &Array<u8, Ix3>) -> Mat<u8> {
+    let layers: Vec<_> = generation.outer_iter().map(slice_to_mat).collect();
+    contact_sheet(&layers, layers.len(), 1, GAP)
+}
+
+/// The layers of a 4d generation, a row of z layers for every w
+fn hyper_layer_sheet(generation: &Array<u8, Ix4>) -> Mat<u8> {
+    let depth = generation.shape()[1];
+    let layers: Vec<_> = generation
+        .outer_iter()
+        .flat_map(|cube| cube.outer_iter().map(slice_to_mat).collect::<Vec<_>>())
+        .collect();
+    contact_sheet(&layers, depth, 1, GAP)
+}
+
+fn cube_glyph(value: &u8) -> char {
+    match *value {
+        0 => '.',
+        1 => '#',
+        _ => ' ',
+    }
+}
+
+fn cube_colour(value: &u8) -> [u8; 3] {
+    match *value {
+        0 => [20, 20, 40],
+        1 => [120, 230, 120],
+        _ => [70, 70, 110],
+    }
+}
+
+#[derive(Debug, Options)]
+struct Arguments {
+    #[options(help = "print help message")]
+    help: bool,
+    #[options(help = "directory to write an image of every generation to")]
+    animate: Option<String>,
+    #[options(help = "step through the generations of the given part in the terminal")]
+    view: Option<usize>,
 }
 
 fn main() {
+    let opts = Arguments::parse_args_default_or_exit();
+    if let Some(part) = opts.view.filter(|&part| part != 1 && part != 2) {
+        println!("there is no part {}", part);
+        return;
+    }
     let start_slice: Array<u8, Ix2> = array![
         [1, 1, 0, 0, 1, 1, 1, 1],
         [0, 1, 1, 1, 0, 0, 0, 0],
@@ -120,8 +189,35 @@ fn main() {
         [0, 0, 1, 0, 1, 0, 1, 0],
         [0, 1, 1, 0, 0, 0, 1, 0],
     ];
-    let result_1 = simulate(&start_slice, 6);
-    println!("Part 1: {}", result_1.sum());
-    let result_2 = simulate2(&start_slice, 6);
-    println!("Part 2: {}", result_2.iter().map(|&v| v as u64).sum::<u64>());
+    let generations_1 = simulate(&start_slice, 6);
+    println!("Part 1: {}", generations_1[generations_1.len() - 1].sum());
+    let generations_2 = simulate2(&start_slice, 6);
+    println!(
+        "Part 2: {}",
+        generations_2[generations_2.len() - 1]
+            .iter()
+            .map(|&v| v as u64)
+            .sum::<u64>()
+    );
+
+    if opts.animate.is_none() && opts.view.is_none() {
+        return;
+    }
+    let sheets_1: Vec<_> = generations_1.iter().map(layer_sheet).collect();
+    let sheets_2: Vec<_> = generations_2.iter().map(hyper_layer_sheet).collect();
+    if let Some(directory) = &opts.animate {
+        if let Err(error) = animate(&sheets_1, Path::new(directory), "part1_", 6, cube_colour)
+            .and_then(|_| animate(&sheets_2, Path::new(directory), "part2_", 6, cube_colour))
+        {
+            println!("could not write animation to '{}': {}", directory, error);
+        }
+    }
+    let view_result = match opts.view {
+        Some(1) => viewer::view(&sheets_1, cube_glyph),
+        Some(_) => viewer::view(&sheets_2, cube_glyph),
+        None => Ok(()),
+    };
+    if let Err(error) = view_result {
+        println!("could not show generations: {}", error);
+    }
 }
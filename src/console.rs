@@ -0,0 +1,607 @@
+pub mod asm;
+pub mod cfg;
+pub mod compiled;
+pub mod debugger;
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use bitvec::prelude::*;
+
+/// Index of the accumulator in the registers
+pub const ACCUMULATOR: usize = 0;
+
+/// Something that went wrong while executing an instruction
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Fault {
+    PcOutOfRange(isize),
+    InvalidRegister(usize),
+    Overflow,
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::PcOutOfRange(pc) => write!(f, "program counter out of range: {}", pc),
+            Fault::InvalidRegister(register) => write!(f, "no such register: {}", register),
+            Fault::Overflow => write!(f, "arithmetic overflow"),
+        }
+    }
+}
+
+/// How a run of a program ended
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Outcome {
+    /// the program counter moved past the last instruction
+    Halt,
+    /// the next instruction was already executed before
+    Loop,
+    Fault(Fault),
+}
+
+/// The program counter and registers of a machine
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cpu {
+    pub pc: isize,
+    pub registers: Vec<isize>,
+}
+
+impl Cpu {
+    pub fn new(num_registers: usize) -> Cpu {
+        Cpu {
+            pc: 0,
+            registers: vec![0; num_registers],
+        }
+    }
+
+    pub fn register(&self, register: usize) -> Result<isize, Fault> {
+        self.registers
+            .get(register)
+            .copied()
+            .ok_or(Fault::InvalidRegister(register))
+    }
+
+    pub fn set_register(&mut self, register: usize, value: isize) -> Result<(), Fault> {
+        let slot = self
+            .registers
+            .get_mut(register)
+            .ok_or(Fault::InvalidRegister(register))?;
+        *slot = value;
+        Ok(())
+    }
+
+    pub fn add_to_register(&mut self, register: usize, value: isize) -> Result<(), Fault> {
+        let sum = self
+            .register(register)?
+            .checked_add(value)
+            .ok_or(Fault::Overflow)?;
+        self.set_register(register, sum)
+    }
+
+    /// Move the program counter by `offset`, 1 being the next instruction
+    pub fn jump(&mut self, offset: isize) -> Result<(), Fault> {
+        self.pc = self.pc.checked_add(offset).ok_or(Fault::Overflow)?;
+        Ok(())
+    }
+}
+
+/// An instruction a `Machine` can run.
+///
+/// The interpreter only ever calls `execute`, so an instruction set with other
+/// opcodes or more registers is just another implementation of this trait.
+/// `Display` should produce source code `parse` accepts.
+pub trait Instruction: Sized + fmt::Display {
+    /// Parse a single line of source code, such as `acc +3`
+    fn parse(line: &str) -> Result<Self, String>;
+
+    /// The name of the operation, such as `acc`
+    fn mnemonic(&self) -> &'static str;
+
+    /// Apply the instruction to `cpu`, including moving the program counter
+    fn execute(&self, cpu: &mut Cpu) -> Result<(), Fault>;
+
+    /// Every pc that can follow this instruction at `pc`, whatever the
+    /// state of the registers; `None` for a jump that overflows the pc
+    fn successors(&self, pc: isize) -> Vec<Option<isize>>;
+}
+
+/// The instruction set of the boot code
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum BootInstruction {
+    Acc(isize),
+    Jmp(isize),
+    Nop(isize),
+}
+
+impl BootInstruction {
+    /// The instruction with `jmp` and `nop` swapped, `None` for `acc`
+    pub fn flipped(self) -> Option<BootInstruction> {
+        match self {
+            BootInstruction::Acc(_) => None,
+            BootInstruction::Jmp(value) => Some(BootInstruction::Nop(value)),
+            BootInstruction::Nop(value) => Some(BootInstruction::Jmp(value)),
+        }
+    }
+}
+
+/// Split a line into a mnemonic and a single signed argument, like `+3` or `-7`
+pub fn parse_operation(line: &str) -> Result<(&str, isize), String> {
+    let mut part_iter = line.split_whitespace();
+    match (part_iter.next(), part_iter.next(), part_iter.next()) {
+        (Some(op_string), Some(offset_string), None) => offset_string
+            .trim_start_matches('+')
+            .parse()
+            .map(|offset| (op_string, offset))
+            .map_err(|_| format!("could not parse offset '{}'", offset_string)),
+        _ => Err(format!(
+            "found unexpected number of words on line '{}'",
+            line
+        )),
+    }
+}
+
+impl Instruction for BootInstruction {
+    fn parse(line: &str) -> Result<Self, String> {
+        let (op_string, offset) = parse_operation(line)?;
+        match op_string {
+            "acc" => Ok(BootInstruction::Acc(offset)),
+            "jmp" => Ok(BootInstruction::Jmp(offset)),
+            "nop" => Ok(BootInstruction::Nop(offset)),
+            _ => Err(format!("could not match operation '{}'", op_string)),
+        }
+    }
+
+    fn mnemonic(&self) -> &'static str {
+        match self {
+            BootInstruction::Acc(_) => "acc",
+            BootInstruction::Jmp(_) => "jmp",
+            BootInstruction::Nop(_) => "nop",
+        }
+    }
+
+    fn execute(&self, cpu: &mut Cpu) -> Result<(), Fault> {
+        match *self {
+            BootInstruction::Acc(value) => {
+                cpu.add_to_register(ACCUMULATOR, value)?;
+                cpu.jump(1)
+            }
+            BootInstruction::Jmp(value) => cpu.jump(value),
+            BootInstruction::Nop(_) => cpu.jump(1),
+        }
+    }
+
+    fn successors(&self, pc: isize) -> Vec<Option<isize>> {
+        match *self {
+            BootInstruction::Jmp(value) => vec![pc.checked_add(value)],
+            BootInstruction::Acc(_) | BootInstruction::Nop(_) => vec![pc.checked_add(1)],
+        }
+    }
+}
+
+impl fmt::Display for BootInstruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BootInstruction::Acc(value)
+            | BootInstruction::Jmp(value)
+            | BootInstruction::Nop(value) => write!(f, "{} {:+}", self.mnemonic(), value),
+        }
+    }
+}
+
+/// The number of steps a machine can undo; older steps are forgotten so
+/// that long runs don't keep growing the log
+pub const HISTORY_LIMIT: usize = 100_000;
+
+/// What is needed to undo a step: the pc before it, the old values of the
+/// registers it changed, and whether it was the first time its instruction
+/// was executed
+#[derive(Clone, Debug)]
+struct Undo {
+    pc: isize,
+    changed: Vec<(usize, isize)>,
+    first_execution: bool,
+}
+
+/// A step in the history of a machine that changed a register
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Change {
+    /// number of steps executed before this one
+    pub step: usize,
+    pub pc: isize,
+    pub old_value: isize,
+}
+
+/// Runs a program, keeping track of which instructions were executed
+pub struct Machine<'a, I> {
+    program: &'a [I],
+    cpu: Cpu,
+    executed: BitVec,
+    history: Option<History>,
+}
+
+/// The undo log of a machine, with the last `HISTORY_LIMIT` steps
+struct History {
+    undo: VecDeque<Undo>,
+    /// the number of steps that were forgotten
+    forgotten: usize,
+    /// the registers before the step being executed
+    registers: Vec<isize>,
+}
+
+impl<'a, I: Instruction> Machine<'a, I> {
+    /// A machine with just the accumulator
+    pub fn new(program: &'a [I]) -> Self {
+        Machine::with_registers(program, 1)
+    }
+
+    /// A machine with `num_registers` registers, the first of which is the
+    /// accumulator; panics if there are none
+    pub fn with_registers(program: &'a [I], num_registers: usize) -> Self {
+        assert!(
+            num_registers > ACCUMULATOR,
+            "a machine needs at least the accumulator register"
+        );
+        Machine {
+            program,
+            cpu: Cpu::new(num_registers),
+            executed: bitvec![0; program.len()],
+            history: None,
+        }
+    }
+
+    /// Keep an undo log of the last `HISTORY_LIMIT` steps from now on, so
+    /// the machine can step backwards
+    pub fn record_history(&mut self) {
+        if self.history.is_none() {
+            self.history = Some(History {
+                undo: VecDeque::new(),
+                forgotten: 0,
+                registers: Vec::new(),
+            });
+        }
+    }
+
+    /// The number of steps that can be undone
+    pub fn history_len(&self) -> usize {
+        self.history
+            .as_ref()
+            .map_or(0, |history| history.undo.len())
+    }
+
+    pub fn program(&self) -> &'a [I] {
+        self.program
+    }
+
+    pub fn cpu(&self) -> &Cpu {
+        &self.cpu
+    }
+
+    pub fn pc(&self) -> isize {
+        self.cpu.pc
+    }
+
+    pub fn accumulator(&self) -> isize {
+        self.cpu.registers[ACCUMULATOR]
+    }
+
+    /// The instruction about to be executed, `None` if the pc is outside the program
+    pub fn current(&self) -> Option<&'a I> {
+        if self.cpu.pc < 0 {
+            None
+        } else {
+            self.program.get(self.cpu.pc as usize)
+        }
+    }
+
+    /// Whether the instruction about to be executed was executed before
+    pub fn at_repeat(&self) -> bool {
+        self.cpu.pc >= 0
+            && self
+                .executed
+                .get(self.cpu.pc as usize)
+                .is_some_and(|bit| *bit)
+    }
+
+    /// Execute the next instruction. Returns the outcome if the machine can
+    /// not continue, which never is `Outcome::Loop`: repeating instructions
+    /// is allowed when stepping.
+    pub fn step(&mut self) -> Option<Outcome> {
+        if self.cpu.pc < 0 {
+            return Some(Outcome::Fault(Fault::PcOutOfRange(self.cpu.pc)));
+        }
+        let index = self.cpu.pc as usize;
+        if index >= self.program.len() {
+            return Some(Outcome::Halt);
+        }
+
+        let first_execution = !self.executed[index];
+        let pc = self.cpu.pc;
+        if let Some(history) = &mut self.history {
+            history.registers.clone_from(&self.cpu.registers);
+        }
+        self.executed.set(index, true);
+        let result = self.program[index].execute(&mut self.cpu);
+
+        if let Some(history) = &mut self.history {
+            let changed = history
+                .registers
+                .iter()
+                .zip(&self.cpu.registers)
+                .enumerate()
+                .filter(|(_, (old, new))| old != new)
+                .map(|(register, (&old, _))| (register, old))
+                .collect();
+            if history.undo.len() == HISTORY_LIMIT {
+                history.undo.pop_front();
+                history.forgotten += 1;
+            }
+            history.undo.push_back(Undo {
+                pc,
+                changed,
+                first_execution,
+            });
+        }
+        result.err().map(Outcome::Fault)
+    }
+
+    /// Undo the last recorded step, returns false if there is none
+    pub fn step_back(&mut self) -> bool {
+        match self
+            .history
+            .as_mut()
+            .and_then(|history| history.undo.pop_back())
+        {
+            Some(undo) => {
+                if undo.first_execution {
+                    self.executed.set(undo.pc as usize, false);
+                }
+                self.cpu.pc = undo.pc;
+                for (register, old_value) in undo.changed {
+                    self.cpu.registers[register] = old_value;
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The last recorded step that changed `register` to `value`
+    pub fn last_change_to(&self, register: usize, value: isize) -> Option<Change> {
+        let history = self.history.as_ref()?;
+        // walking back, the value of the register after the step
+        let mut after = *self.cpu.registers.get(register)?;
+        for (index, undo) in history.undo.iter().enumerate().rev() {
+            if let Some(&(_, old_value)) = undo
+                .changed
+                .iter()
+                .find(|&&(changed, _)| changed == register)
+            {
+                if after == value {
+                    return Some(Change {
+                        step: history.forgotten + index,
+                        pc: undo.pc,
+                        old_value,
+                    });
+                }
+                after = old_value;
+            }
+        }
+        None
+    }
+
+    /// Run until the program halts, faults or is about to execute an
+    /// instruction for the second time
+    pub fn run(&mut self) -> Outcome {
+        loop {
+            if self.at_repeat() {
+                return Outcome::Loop;
+            }
+            if let Some(outcome) = self.step() {
+                return outcome;
+            }
+        }
+    }
+}
+
+/// The example program from the puzzle, which loops after reaching an
+/// accumulator of 5
+pub static EXAMPLE: &str = "nop +0
+acc +1
+jmp +4
+acc +3
+jmp -3
+acc -99
+acc +1
+jmp -4
+acc +6
+";
+
+/// Parse a program with one instruction per line, for tests
+#[cfg(test)]
+pub(crate) fn parse_program<I: Instruction>(source: &str) -> Vec<I> {
+    source.lines().map(|line| I::parse(line).unwrap()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn example_loops() {
+        let program: Vec<BootInstruction> = parse_program(EXAMPLE);
+        let mut machine = Machine::new(&program);
+        assert_eq!(machine.run(), Outcome::Loop);
+        assert_eq!(machine.accumulator(), 5);
+    }
+
+    #[test]
+    fn step_back() {
+        let program: Vec<BootInstruction> = parse_program(EXAMPLE);
+        let mut machine = Machine::new(&program);
+        machine.record_history();
+        assert_eq!(machine.run(), Outcome::Loop);
+        assert_eq!(machine.history_len(), 7);
+
+        // acc +3 at pc 3 took the accumulator from 2 to 5
+        assert_eq!(
+            machine.last_change_to(ACCUMULATOR, 5),
+            Some(Change {
+                step: 5,
+                pc: 3,
+                old_value: 2
+            })
+        );
+        assert_eq!(machine.last_change_to(ACCUMULATOR, 4), None);
+
+        assert!(machine.step_back());
+        assert!(machine.step_back());
+        assert_eq!((machine.pc(), machine.accumulator()), (3, 2));
+        assert!(!machine.at_repeat());
+        assert_eq!(machine.run(), Outcome::Loop);
+        assert_eq!(machine.accumulator(), 5);
+
+        while machine.step_back() {}
+        assert_eq!((machine.pc(), machine.accumulator()), (0, 0));
+        assert!(!machine.at_repeat());
+    }
+
+    #[test]
+    fn history_is_limited() {
+        let program: Vec<BootInstruction> = parse_program("acc +1\njmp -1");
+        let mut machine = Machine::new(&program);
+        machine.record_history();
+        for _ in 0..HISTORY_LIMIT + 10 {
+            assert_eq!(machine.step(), None);
+        }
+        assert_eq!(machine.history_len(), HISTORY_LIMIT);
+        assert_eq!(machine.accumulator(), (HISTORY_LIMIT / 2 + 5) as isize);
+
+        let value = machine.accumulator();
+        assert_eq!(
+            machine.last_change_to(ACCUMULATOR, value),
+            Some(Change {
+                step: HISTORY_LIMIT + 8,
+                pc: 0,
+                old_value: value - 1
+            })
+        );
+        assert_eq!(machine.last_change_to(ACCUMULATOR, 3), None);
+
+        while machine.step_back() {}
+        assert_eq!((machine.pc(), machine.accumulator()), (0, 5));
+    }
+
+    #[test]
+    fn negative_pc_faults() {
+        let program: Vec<BootInstruction> = parse_program("nop +0\njmp -2");
+        assert_eq!(
+            Machine::new(&program).run(),
+            Outcome::Fault(Fault::PcOutOfRange(-1))
+        );
+    }
+
+    /// An instruction set with more registers, multiplication and a
+    /// conditional jump, run by the same machine
+    enum Extended {
+        Boot(BootInstruction),
+        Set(usize, isize),
+        Mul(usize, usize),
+        Dec(usize),
+        Jnz(usize, isize),
+    }
+
+    impl fmt::Display for Extended {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self {
+                Extended::Boot(instruction) => write!(f, "{}", instruction),
+                Extended::Set(register, value) => write!(f, "set {} {}", register, value),
+                Extended::Mul(target, source) => write!(f, "mul {} {}", target, source),
+                Extended::Dec(register) => write!(f, "dec {}", register),
+                Extended::Jnz(register, offset) => write!(f, "jnz {} {:+}", register, offset),
+            }
+        }
+    }
+
+    impl Instruction for Extended {
+        fn parse(line: &str) -> Result<Self, String> {
+            let words: Vec<_> = line.split_whitespace().collect();
+            let number = |i: usize| -> Result<isize, String> {
+                words
+                    .get(i)
+                    .and_then(|word| word.trim_start_matches('+').parse().ok())
+                    .ok_or(format!("bad operand in '{}'", line))
+            };
+            match words.first() {
+                Some(&"set") => Ok(Extended::Set(number(1)? as usize, number(2)?)),
+                Some(&"mul") => Ok(Extended::Mul(number(1)? as usize, number(2)? as usize)),
+                Some(&"dec") => Ok(Extended::Dec(number(1)? as usize)),
+                Some(&"jnz") => Ok(Extended::Jnz(number(1)? as usize, number(2)?)),
+                _ => BootInstruction::parse(line).map(Extended::Boot),
+            }
+        }
+
+        fn mnemonic(&self) -> &'static str {
+            match self {
+                Extended::Boot(instruction) => instruction.mnemonic(),
+                Extended::Set(..) => "set",
+                Extended::Mul(..) => "mul",
+                Extended::Dec(..) => "dec",
+                Extended::Jnz(..) => "jnz",
+            }
+        }
+
+        fn execute(&self, cpu: &mut Cpu) -> Result<(), Fault> {
+            match *self {
+                Extended::Boot(instruction) => return instruction.execute(cpu),
+                Extended::Set(register, value) => cpu.set_register(register, value)?,
+                Extended::Mul(target, source) => {
+                    let product = cpu
+                        .register(target)?
+                        .checked_mul(cpu.register(source)?)
+                        .ok_or(Fault::Overflow)?;
+                    cpu.set_register(target, product)?
+                }
+                Extended::Dec(register) => cpu.add_to_register(register, -1)?,
+                Extended::Jnz(register, offset) => {
+                    if cpu.register(register)? != 0 {
+                        return cpu.jump(offset);
+                    }
+                }
+            }
+            cpu.jump(1)
+        }
+
+        fn successors(&self, pc: isize) -> Vec<Option<isize>> {
+            match *self {
+                Extended::Boot(instruction) => instruction.successors(pc),
+                Extended::Jnz(_, offset) => vec![pc.checked_add(1), pc.checked_add(offset)],
+                _ => vec![pc.checked_add(1)],
+            }
+        }
+    }
+
+    #[test]
+    fn extended_instruction_set() {
+        // 5! in the accumulator, counting down in register 1
+        let program: Vec<Extended> = parse_program(
+            "acc +1
+set 1 5
+mul 0 1
+dec 1
+jnz 1 -2",
+        );
+        let mut machine = Machine::with_registers(&program, 2);
+        while machine.step().is_none() {}
+        assert_eq!(machine.accumulator(), 120);
+
+        let mut machine = Machine::with_registers(&program, 1);
+        assert_eq!(machine.run(), Outcome::Fault(Fault::InvalidRegister(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "accumulator")]
+    fn no_registers() {
+        let program: Vec<BootInstruction> = Vec::new();
+        Machine::with_registers(&program, 0);
+    }
+}
@@ -0,0 +1,162 @@
+use super::Instruction;
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// A problem in the source of a program, with its 1-based line number
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn is_label(word: &str) -> bool {
+    let mut chars = word.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// A source line without its comment, split in label definitions and the
+/// remaining instruction text
+fn split_line(line: &str) -> (Vec<&str>, &str) {
+    let code = line.split(['#', ';']).next().unwrap_or("");
+    let mut labels = Vec::new();
+    let mut rest = code.trim();
+    while let Some(colon) = rest.find(':') {
+        let label = rest[..colon].trim();
+        if !is_label(label) {
+            break;
+        }
+        labels.push(label);
+        rest = rest[colon + 1..].trim();
+    }
+    (labels, rest)
+}
+
+/// Assemble a program from source code.
+///
+/// Besides what `Instruction::parse` accepts, the source may contain
+/// comments starting with `#` or `;`, and label definitions (`loop:`) on
+/// their own line or in front of an instruction. A label used as an operand
+/// (`jmp loop`) is replaced by the offset from the instruction to the label,
+/// so it works for any operand that is relative to the pc. A label after the
+/// last instruction refers to the end of the program.
+pub fn assemble<I: Instruction>(source: &str) -> Result<Vec<I>, Vec<AsmError>> {
+    let mut errors = Vec::new();
+    let mut labels = BTreeMap::new();
+    let mut instructions = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let (line_labels, code) = split_line(line);
+        for label in line_labels {
+            if labels.insert(label, instructions.len() as isize).is_some() {
+                errors.push(AsmError {
+                    line: index + 1,
+                    message: format!("label '{}' is defined more than once", label),
+                });
+            }
+        }
+        if !code.is_empty() {
+            instructions.push((index + 1, code));
+        }
+    }
+
+    let mut program = Vec::with_capacity(instructions.len());
+    for (pc, &(line, code)) in instructions.iter().enumerate() {
+        let mut words = code.split_whitespace();
+        let mut resolved: Vec<String> = words.next().map(String::from).into_iter().collect();
+        let mut unresolved = false;
+        for word in words {
+            if is_label(word) {
+                match labels.get(word) {
+                    Some(&target) => resolved.push(format!("{:+}", target - pc as isize)),
+                    None => {
+                        errors.push(AsmError {
+                            line,
+                            message: format!("unknown label '{}'", word),
+                        });
+                        unresolved = true;
+                    }
+                }
+            } else {
+                resolved.push(word.to_string());
+            }
+        }
+        if unresolved {
+            continue;
+        }
+        match I::parse(&resolved.join(" ")) {
+            Ok(instruction) => program.push(instruction),
+            Err(message) => errors.push(AsmError { line, message }),
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(program)
+    } else {
+        errors.sort_by_key(|error| error.line);
+        Err(errors)
+    }
+}
+
+/// The canonical source of a program: one instruction per line, numeric
+/// offsets, no labels or comments. `assemble` turns it back into the same
+/// program.
+pub fn disassemble<I: Instruction>(program: &[I]) -> String {
+    program
+        .iter()
+        .map(|instruction| format!("{}\n", instruction))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::{BootInstruction, EXAMPLE};
+
+    #[test]
+    fn labels_and_comments() {
+        let source = "# the example program
+start:  nop +0
+again:  acc +1      ; counts the visits
+        jmp skip
+back:   acc +3
+        jmp again
+        acc -99
+skip:   acc +1
+        jmp back
+        acc +6
+";
+        let program: Vec<BootInstruction> = assemble(source).unwrap();
+        assert_eq!(disassemble(&program), EXAMPLE);
+        assert_eq!(assemble::<BootInstruction>(EXAMPLE).unwrap(), program);
+    }
+
+    #[test]
+    fn errors_have_line_numbers() {
+        let source = "a: nop +0\na: jmp nowhere\nmul +3\n";
+        let errors = assemble::<BootInstruction>(source).unwrap_err();
+        let lines: Vec<_> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, vec![2, 2, 3]);
+    }
+
+    #[test]
+    fn round_trip() {
+        let program: Vec<BootInstruction> = (-20isize..20)
+            .map(|value| match value.rem_euclid(3) {
+                0 => BootInstruction::Acc(value),
+                1 => BootInstruction::Jmp(value),
+                _ => BootInstruction::Nop(value),
+            })
+            .collect();
+        let source = disassemble(&program);
+        assert_eq!(assemble::<BootInstruction>(&source).unwrap(), program);
+    }
+}
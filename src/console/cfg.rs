@@ -0,0 +1,281 @@
+use super::Instruction;
+
+use std::collections::BTreeSet;
+use std::fmt::Write;
+use std::ops::Range;
+
+/// Where control goes after a basic block
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Target {
+    Block(usize),
+    /// past the end of the program, so it halts
+    Exit,
+    /// before the start of the program or beyond the range of the pc, so it
+    /// faults
+    Fault,
+}
+
+/// A run of instructions that is only ever entered at the first one and left
+/// after the last one
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct BasicBlock {
+    pub instructions: Range<usize>,
+    pub successors: Vec<Target>,
+}
+
+/// The control-flow graph of a program
+pub struct ControlFlowGraph {
+    blocks: Vec<BasicBlock>,
+    reachable: Vec<bool>,
+    reaches_exit: Vec<bool>,
+    reaches_fault: Vec<bool>,
+}
+
+impl ControlFlowGraph {
+    pub fn new<I: Instruction>(program: &[I]) -> ControlFlowGraph {
+        let len = program.len() as isize;
+        let mut leaders = BTreeSet::new();
+        if !program.is_empty() {
+            leaders.insert(0);
+        }
+        for (pc, instruction) in program.iter().enumerate() {
+            let successors = instruction.successors(pc as isize);
+            if successors != [Some(pc as isize + 1)] {
+                leaders.extend(
+                    successors
+                        .into_iter()
+                        .flatten()
+                        .chain(std::iter::once(pc as isize + 1))
+                        .filter(|&next| next >= 0 && next < len),
+                );
+            }
+        }
+
+        let starts: Vec<usize> = leaders.into_iter().map(|pc| pc as usize).collect();
+        let mut block_of = vec![0; program.len()];
+        for (block, &start) in starts.iter().enumerate() {
+            let end = starts.get(block + 1).copied().unwrap_or(program.len());
+            for entry in &mut block_of[start..end] {
+                *entry = block;
+            }
+        }
+
+        let blocks: Vec<BasicBlock> = starts
+            .iter()
+            .enumerate()
+            .map(|(block, &start)| {
+                let end = starts.get(block + 1).copied().unwrap_or(program.len());
+                let last = end - 1;
+                let successors: BTreeSet<Target> = program[last]
+                    .successors(last as isize)
+                    .into_iter()
+                    .map(|next| match next {
+                        Some(next) if next >= len => Target::Exit,
+                        Some(next) if next >= 0 => Target::Block(block_of[next as usize]),
+                        _ => Target::Fault,
+                    })
+                    .collect();
+                BasicBlock {
+                    instructions: start..end,
+                    successors: successors.into_iter().collect(),
+                }
+            })
+            .collect();
+
+        let mut reachable = vec![false; blocks.len()];
+        let mut stack = if blocks.is_empty() {
+            Vec::new()
+        } else {
+            vec![0]
+        };
+        while let Some(block) = stack.pop() {
+            if !reachable[block] {
+                reachable[block] = true;
+                for &target in &blocks[block].successors {
+                    if let Target::Block(next) = target {
+                        stack.push(next);
+                    }
+                }
+            }
+        }
+
+        let reaches_exit = reaches(&blocks, Target::Exit);
+        let reaches_fault = reaches(&blocks, Target::Fault);
+        ControlFlowGraph {
+            blocks,
+            reachable,
+            reaches_exit,
+            reaches_fault,
+        }
+    }
+
+    pub fn blocks(&self) -> &[BasicBlock] {
+        &self.blocks
+    }
+
+    /// Whether the block can be executed when starting at pc 0
+    pub fn is_reachable(&self, block: usize) -> bool {
+        self.reachable[block]
+    }
+
+    /// Whether the program can halt after executing the block
+    pub fn can_halt(&self, block: usize) -> bool {
+        self.reaches_exit[block]
+    }
+
+    /// The instructions that can never be executed
+    pub fn dead_code(&self) -> Vec<Range<usize>> {
+        (0..self.blocks.len())
+            .filter(|&block| !self.reachable[block])
+            .map(|block| self.blocks[block].instructions.clone())
+            .collect()
+    }
+
+    /// Reachable blocks that once entered can neither halt nor fault, so the
+    /// program loops forever when it gets there
+    pub fn infinite_loops(&self) -> Vec<usize> {
+        (0..self.blocks.len())
+            .filter(|&block| {
+                self.reachable[block] && !self.reaches_exit[block] && !self.reaches_fault[block]
+            })
+            .collect()
+    }
+
+    /// Graphviz source for the graph. Unreachable blocks are grey, blocks
+    /// that can only loop forever are red.
+    pub fn to_dot<I: Instruction>(&self, program: &[I]) -> String {
+        let mut dot =
+            String::from("digraph program {\n    node [shape=box fontname=\"monospace\"];\n");
+        let loops: BTreeSet<usize> = self.infinite_loops().into_iter().collect();
+        for (index, block) in self.blocks.iter().enumerate() {
+            let label: String = block
+                .instructions
+                .clone()
+                .map(|pc| format!("{}: {}\\l", pc, program[pc]))
+                .collect();
+            let style = if !self.reachable[index] {
+                " style=filled fillcolor=lightgrey"
+            } else if loops.contains(&index) {
+                " style=filled fillcolor=salmon"
+            } else {
+                ""
+            };
+            writeln!(dot, "    b{} [label=\"{}\"{}];", index, label, style).unwrap();
+        }
+
+        let mut uses_exit = false;
+        let mut uses_fault = false;
+        for (index, block) in self.blocks.iter().enumerate() {
+            for target in &block.successors {
+                let name = match target {
+                    Target::Block(next) => format!("b{}", next),
+                    Target::Exit => {
+                        uses_exit = true;
+                        String::from("exit")
+                    }
+                    Target::Fault => {
+                        uses_fault = true;
+                        String::from("fault")
+                    }
+                };
+                writeln!(dot, "    b{} -> {};", index, name).unwrap();
+            }
+        }
+        if uses_exit {
+            dot.push_str("    exit [shape=doublecircle];\n");
+        }
+        if uses_fault {
+            dot.push_str("    fault [shape=octagon];\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// For every block, whether `target` can be reached from it
+fn reaches(blocks: &[BasicBlock], target: Target) -> Vec<bool> {
+    let mut predecessors = vec![Vec::new(); blocks.len()];
+    let mut result = vec![false; blocks.len()];
+    let mut stack = Vec::new();
+    for (index, block) in blocks.iter().enumerate() {
+        for &next in &block.successors {
+            if next == target {
+                stack.push(index);
+            } else if let Target::Block(next) = next {
+                predecessors[next].push(index);
+            }
+        }
+    }
+    while let Some(block) = stack.pop() {
+        if !result[block] {
+            result[block] = true;
+            stack.extend(&predecessors[block]);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::{parse_program, BootInstruction, EXAMPLE};
+
+    #[test]
+    fn example_graph() {
+        let program: Vec<BootInstruction> = parse_program(EXAMPLE);
+        let cfg = ControlFlowGraph::new(&program);
+        let ranges: Vec<_> = cfg
+            .blocks()
+            .iter()
+            .map(|block| block.instructions.clone())
+            .collect();
+        assert_eq!(ranges, vec![0..1, 1..3, 3..5, 5..6, 6..8, 8..9]);
+        assert_eq!(cfg.dead_code(), vec![5..6, 8..9]);
+        assert_eq!(cfg.infinite_loops(), vec![0, 1, 2, 4]);
+        assert!(cfg.can_halt(5));
+        assert_eq!(
+            cfg.to_dot(&program),
+            "digraph program {
+    node [shape=box fontname=\"monospace\"];
+    b0 [label=\"0: nop +0\\l\" style=filled fillcolor=salmon];
+    b1 [label=\"1: acc +1\\l2: jmp +4\\l\" style=filled fillcolor=salmon];
+    b2 [label=\"3: acc +3\\l4: jmp -3\\l\" style=filled fillcolor=salmon];
+    b3 [label=\"5: acc -99\\l\" style=filled fillcolor=lightgrey];
+    b4 [label=\"6: acc +1\\l7: jmp -4\\l\" style=filled fillcolor=salmon];
+    b5 [label=\"8: acc +6\\l\" style=filled fillcolor=lightgrey];
+    b0 -> b1;
+    b1 -> b4;
+    b2 -> b1;
+    b3 -> b4;
+    b4 -> b2;
+    b5 -> exit;
+    exit [shape=doublecircle];
+}
+"
+        );
+    }
+
+    #[test]
+    fn overflowing_jump_faults() {
+        let source = "nop +0\njmp +2\njmp +9223372036854775807\nacc +1\n";
+        let program: Vec<BootInstruction> = parse_program(source);
+        let cfg = ControlFlowGraph::new(&program);
+        assert_eq!(cfg.blocks()[1].successors, vec![Target::Fault]);
+        assert_eq!(cfg.dead_code(), vec![2..3]);
+        assert_eq!(
+            cfg.to_dot(&program),
+            "digraph program {
+    node [shape=box fontname=\"monospace\"];
+    b0 [label=\"0: nop +0\\l1: jmp +2\\l\"];
+    b1 [label=\"2: jmp +9223372036854775807\\l\" style=filled fillcolor=lightgrey];
+    b2 [label=\"3: acc +1\\l\"];
+    b0 -> b2;
+    b1 -> fault;
+    b2 -> exit;
+    exit [shape=doublecircle];
+    fault [shape=octagon];
+}
+"
+        );
+    }
+}
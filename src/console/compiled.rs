@@ -0,0 +1,142 @@
+use super::{BootInstruction, Fault, Outcome};
+
+/// Every boot instruction adds something to the accumulator (`nop` and `jmp`
+/// add zero) and then continues at a fixed index
+#[derive(Clone, Copy, Debug)]
+struct Operation {
+    add: isize,
+    next: usize,
+}
+
+/// Where an operation continues when its jump overflows the pc
+const OVERFLOW: usize = usize::MAX;
+
+/// Boot code decoded for programs where interpreting dominates.
+///
+/// Jump targets are resolved to absolute indices when compiling: every target
+/// past the end becomes the halt index `len`, and every target before the
+/// start an index above it that identifies the faulting pc. The run loop then
+/// only needs a single comparison to know whether it can keep going.
+pub struct CompiledProgram {
+    operations: Vec<Operation>,
+    fault_targets: Vec<isize>,
+}
+
+impl CompiledProgram {
+    pub fn new(program: &[BootInstruction]) -> CompiledProgram {
+        let len = program.len();
+        let mut fault_targets = Vec::new();
+        let mut resolve = |target: Option<isize>| -> usize {
+            match target {
+                Some(target) if target < 0 => {
+                    fault_targets.push(target);
+                    len + fault_targets.len()
+                }
+                Some(target) => (target as usize).min(len),
+                None => OVERFLOW,
+            }
+        };
+
+        let operations = program
+            .iter()
+            .enumerate()
+            .map(|(pc, &instruction)| {
+                let pc = pc as isize;
+                match instruction {
+                    BootInstruction::Acc(value) => Operation {
+                        add: value,
+                        next: resolve(Some(pc + 1)),
+                    },
+                    BootInstruction::Jmp(offset) => Operation {
+                        add: 0,
+                        next: resolve(pc.checked_add(offset)),
+                    },
+                    BootInstruction::Nop(_) => Operation {
+                        add: 0,
+                        next: resolve(Some(pc + 1)),
+                    },
+                }
+            })
+            .collect();
+
+        CompiledProgram {
+            operations,
+            fault_targets,
+        }
+    }
+
+    /// Like `Machine::run`: the outcome, and the final accumulator
+    pub fn run(&self) -> (Outcome, isize) {
+        let len = self.operations.len();
+        let mut executed = vec![false; len];
+        let mut accumulator: isize = 0;
+        let mut index = 0;
+        while index < len {
+            if executed[index] {
+                return (Outcome::Loop, accumulator);
+            }
+            executed[index] = true;
+            let operation = self.operations[index];
+            accumulator = match accumulator.checked_add(operation.add) {
+                Some(sum) => sum,
+                None => return (Outcome::Fault(Fault::Overflow), accumulator),
+            };
+            index = operation.next;
+        }
+
+        let outcome = if index == len {
+            Outcome::Halt
+        } else if index == OVERFLOW {
+            Outcome::Fault(Fault::Overflow)
+        } else {
+            Outcome::Fault(Fault::PcOutOfRange(self.fault_targets[index - len - 1]))
+        };
+        (outcome, accumulator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::Machine;
+    use crate::random::Lcg;
+
+    fn random_program(rng: &mut Lcg) -> Vec<BootInstruction> {
+        let len = 1 + rng.below(40) as isize;
+        (0..len)
+            .map(|_| {
+                let value = rng.below(2 * len as u64 + 5) as isize - len - 2;
+                match rng.below(3) {
+                    0 => BootInstruction::Acc(value),
+                    1 => BootInstruction::Jmp(value),
+                    _ => BootInstruction::Nop(value),
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn agrees_with_interpreter() {
+        let mut rng = Lcg::new(2020);
+        for _ in 0..5000 {
+            let program = random_program(&mut rng);
+            let mut machine = Machine::new(&program);
+            let outcome = machine.run();
+            assert_eq!(
+                CompiledProgram::new(&program).run(),
+                (outcome, machine.accumulator()),
+                "program: {:?}",
+                program
+            );
+        }
+    }
+
+    #[test]
+    fn overflow_faults() {
+        let program = vec![BootInstruction::Acc(isize::MAX), BootInstruction::Acc(1)];
+        assert_eq!(
+            CompiledProgram::new(&program).run(),
+            (Outcome::Fault(Fault::Overflow), isize::MAX)
+        );
+    }
+}
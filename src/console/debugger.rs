@@ -0,0 +1,347 @@
+use super::{Instruction, Machine, Outcome, ACCUMULATOR};
+
+use std::collections::BTreeSet;
+use std::io::{self, BufRead, Write};
+
+/// `continue` gives up after this many steps without hitting a breakpoint
+const MAX_CONTINUE_STEPS: usize = 10_000_000;
+
+static HELP: &str = "commands:
+  s, step [n]          execute n instructions (default 1)
+  c, continue          run until a breakpoint, a watched change or the end
+  u, until             run until an instruction is about to execute a second time
+  back [n]             undo the last n steps (default 1)
+  who <value>          find the step that last set the accumulator to value
+  b, break <pc|op>     break before executing the instruction at pc, or any op
+  d, delete <pc|op>    remove a breakpoint
+  w, watch             toggle stopping when the accumulator changes
+  p, print             show the pc, the next instruction and the registers
+  l, list [n]          show n instructions around the pc (default 5)
+  i, info              show breakpoints and watches
+  q, quit              leave the debugger";
+
+/// Execute one instruction, printing the pc, the instruction and the
+/// accumulator after executing it
+pub fn trace_step<I: Instruction>(
+    machine: &mut Machine<I>,
+    out: &mut impl Write,
+) -> io::Result<Option<Outcome>> {
+    let pc = machine.pc();
+    let instruction = machine.current().map(|instruction| instruction.to_string());
+    let outcome = machine.step();
+    if let Some(instruction) = instruction {
+        writeln!(
+            out,
+            "{:>5}  {:<12} acc = {}",
+            pc,
+            instruction,
+            machine.accumulator()
+        )?;
+    }
+    Ok(outcome)
+}
+
+/// Like `Machine::run`, but printing every step
+pub fn trace<I: Instruction>(
+    machine: &mut Machine<I>,
+    out: &mut impl Write,
+) -> io::Result<Outcome> {
+    loop {
+        if machine.at_repeat() {
+            return Ok(Outcome::Loop);
+        }
+        if let Some(outcome) = trace_step(machine, out)? {
+            return Ok(outcome);
+        }
+    }
+}
+
+#[derive(Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Breakpoint {
+    Pc(isize),
+    Op(String),
+}
+
+impl Breakpoint {
+    fn parse(argument: &str) -> Breakpoint {
+        match argument.trim_start_matches('+').parse() {
+            Ok(pc) => Breakpoint::Pc(pc),
+            Err(_) => Breakpoint::Op(argument.to_string()),
+        }
+    }
+}
+
+/// Why the machine stopped after a command
+enum Stop {
+    Done,
+    Breakpoint(Breakpoint),
+    Watch(isize, isize),
+    Repeat,
+    Outcome(Outcome),
+    Limit,
+}
+
+/// A command line debugger for a `Machine`
+pub struct Debugger<'a, I> {
+    machine: Machine<'a, I>,
+    breakpoints: BTreeSet<Breakpoint>,
+    watch_accumulator: bool,
+}
+
+impl<'a, I: Instruction> Debugger<'a, I> {
+    pub fn new(mut machine: Machine<'a, I>) -> Self {
+        machine.record_history();
+        Debugger {
+            machine,
+            breakpoints: BTreeSet::new(),
+            watch_accumulator: false,
+        }
+    }
+
+    pub fn machine(&self) -> &Machine<'a, I> {
+        &self.machine
+    }
+
+    fn breakpoint_hit(&self) -> Option<Breakpoint> {
+        let pc = Breakpoint::Pc(self.machine.pc());
+        if self.breakpoints.contains(&pc) {
+            return Some(pc);
+        }
+        let op = Breakpoint::Op(self.machine.current()?.mnemonic().to_string());
+        if self.breakpoints.contains(&op) {
+            Some(op)
+        } else {
+            None
+        }
+    }
+
+    /// Execute one instruction, reporting a change of a watched accumulator
+    fn step(&mut self) -> Stop {
+        let before = self.machine.accumulator();
+        if let Some(outcome) = self.machine.step() {
+            return Stop::Outcome(outcome);
+        }
+        let after = self.machine.accumulator();
+        if self.watch_accumulator && before != after {
+            Stop::Watch(before, after)
+        } else {
+            Stop::Done
+        }
+    }
+
+    fn step_n(&mut self, n: usize) -> Stop {
+        for _ in 0..n {
+            match self.step() {
+                Stop::Done => {}
+                stop => return stop,
+            }
+        }
+        Stop::Done
+    }
+
+    fn continue_running(&mut self) -> Stop {
+        for i in 0..MAX_CONTINUE_STEPS {
+            // don't stop at the breakpoint we are standing on
+            if i > 0 {
+                if let Some(breakpoint) = self.breakpoint_hit() {
+                    return Stop::Breakpoint(breakpoint);
+                }
+            }
+            match self.step() {
+                Stop::Done => {}
+                stop => return stop,
+            }
+        }
+        Stop::Limit
+    }
+
+    fn run_until_repeat(&mut self) -> Stop {
+        loop {
+            if self.machine.at_repeat() {
+                return Stop::Repeat;
+            }
+            if let Some(outcome) = self.machine.step() {
+                return Stop::Outcome(outcome);
+            }
+        }
+    }
+
+    fn print_state(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.machine.current() {
+            Some(instruction) => writeln!(out, "pc {}: {}", self.machine.pc(), instruction)?,
+            None => writeln!(out, "pc {}: outside the program", self.machine.pc())?,
+        }
+        writeln!(out, "registers: {:?}", self.machine.cpu().registers)
+    }
+
+    fn list(&self, out: &mut impl Write, context: usize) -> io::Result<()> {
+        let pc = self.machine.pc();
+        let program = self.machine.program();
+        let first = (pc - context as isize).max(0);
+        let last = (pc + context as isize).min(program.len() as isize - 1);
+        for index in first..=last {
+            let marker = if index == pc { "=>" } else { "  " };
+            writeln!(out, "{} {:>5}  {}", marker, index, program[index as usize])?;
+        }
+        Ok(())
+    }
+
+    fn report(&self, stop: Stop, out: &mut impl Write) -> io::Result<()> {
+        match stop {
+            Stop::Done => {}
+            Stop::Breakpoint(Breakpoint::Pc(pc)) => writeln!(out, "breakpoint at pc {}", pc)?,
+            Stop::Breakpoint(Breakpoint::Op(op)) => writeln!(out, "breakpoint on '{}'", op)?,
+            Stop::Watch(before, after) => {
+                writeln!(out, "accumulator changed: {} -> {}", before, after)?
+            }
+            Stop::Repeat => writeln!(
+                out,
+                "about to execute pc {} a second time",
+                self.machine.pc()
+            )?,
+            Stop::Outcome(Outcome::Halt) => writeln!(out, "program halted")?,
+            Stop::Outcome(Outcome::Loop) => writeln!(out, "program loops")?,
+            Stop::Outcome(Outcome::Fault(fault)) => writeln!(out, "fault: {}", fault)?,
+            Stop::Limit => writeln!(out, "still running after {} steps", MAX_CONTINUE_STEPS)?,
+        }
+        self.print_state(out)
+    }
+
+    /// Execute a single command line, returns false when the user quits
+    pub fn command(&mut self, line: &str, out: &mut impl Write) -> io::Result<bool> {
+        let mut words = line.split_whitespace();
+        let command = match words.next() {
+            Some(command) => command,
+            None => return Ok(true),
+        };
+        let argument = words.next();
+        let count = |default| argument.and_then(|a| a.parse().ok()).unwrap_or(default);
+
+        match command {
+            "s" | "step" => {
+                let stop = self.step_n(count(1));
+                self.report(stop, out)?;
+            }
+            "c" | "continue" => {
+                let stop = self.continue_running();
+                self.report(stop, out)?;
+            }
+            "u" | "until" => {
+                let stop = self.run_until_repeat();
+                self.report(stop, out)?;
+            }
+            "back" => {
+                let steps = count(1);
+                let undone = (0..steps).take_while(|_| self.machine.step_back()).count();
+                if undone < steps {
+                    writeln!(out, "at the start of the recorded history")?;
+                }
+                self.print_state(out)?;
+            }
+            "who" => match argument.and_then(|a| a.trim_start_matches('+').parse().ok()) {
+                Some(value) => match self.machine.last_change_to(ACCUMULATOR, value) {
+                    Some(change) => {
+                        let instruction = &self.machine.program()[change.pc as usize];
+                        writeln!(
+                            out,
+                            "step {}: pc {} ({}) changed the accumulator from {} to {}",
+                            change.step, change.pc, instruction, change.old_value, value
+                        )?
+                    }
+                    None => writeln!(out, "the accumulator was never changed to {}", value)?,
+                },
+                None => writeln!(out, "expected a value")?,
+            },
+            "b" | "break" | "d" | "delete" => match argument {
+                Some(argument) => {
+                    let breakpoint = Breakpoint::parse(argument);
+                    if command.starts_with('b') {
+                        self.breakpoints.insert(breakpoint);
+                    } else if !self.breakpoints.remove(&breakpoint) {
+                        writeln!(out, "no breakpoint '{}'", argument)?;
+                    }
+                }
+                None => writeln!(out, "expected a pc or an operation")?,
+            },
+            "w" | "watch" => {
+                self.watch_accumulator = !self.watch_accumulator;
+                let state = if self.watch_accumulator { "on" } else { "off" };
+                writeln!(out, "watching the accumulator: {}", state)?;
+            }
+            "p" | "print" => self.print_state(out)?,
+            "l" | "list" => self.list(out, count(5))?,
+            "i" | "info" => {
+                for breakpoint in &self.breakpoints {
+                    match breakpoint {
+                        Breakpoint::Pc(pc) => writeln!(out, "break at pc {}", pc)?,
+                        Breakpoint::Op(op) => writeln!(out, "break on '{}'", op)?,
+                    }
+                }
+                if self.watch_accumulator {
+                    writeln!(out, "watching the accumulator")?;
+                }
+            }
+            "q" | "quit" => return Ok(false),
+            "h" | "help" => writeln!(out, "{}", HELP)?,
+            _ => writeln!(out, "unknown command '{}', try 'help'", command)?,
+        }
+        Ok(true)
+    }
+
+    /// Read commands from `input` until it ends or the user quits
+    pub fn repl(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        self.print_state(out)?;
+        write!(out, "(debug) ")?;
+        out.flush()?;
+        for line in input.lines() {
+            if !self.command(&line?, out)? {
+                break;
+            }
+            write!(out, "(debug) ")?;
+            out.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::console::{parse_program, BootInstruction, EXAMPLE};
+
+    #[test]
+    fn breakpoints_and_watch() {
+        let program: Vec<BootInstruction> = parse_program(EXAMPLE);
+        let mut debugger = Debugger::new(Machine::new(&program));
+        let mut out = Vec::new();
+
+        debugger.command("break 4", &mut out).unwrap();
+        debugger.command("continue", &mut out).unwrap();
+        assert_eq!(debugger.machine().pc(), 4);
+        assert_eq!(debugger.machine().accumulator(), 5);
+
+        debugger.command("delete 4", &mut out).unwrap();
+        debugger.command("break jmp", &mut out).unwrap();
+        debugger.command("c", &mut out).unwrap();
+        assert_eq!(debugger.machine().pc(), 2);
+
+        debugger.command("watch", &mut out).unwrap();
+        debugger.command("step 10", &mut out).unwrap();
+        assert_eq!(debugger.machine().pc(), 7);
+        assert_eq!(debugger.machine().accumulator(), 7);
+
+        debugger.command("until", &mut out).unwrap();
+        assert!(debugger.machine().at_repeat());
+
+        debugger.command("back 2", &mut out).unwrap();
+        assert_eq!(debugger.machine().pc(), 2);
+        assert_eq!(debugger.machine().accumulator(), 6);
+        out.clear();
+        debugger.command("who 6", &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out.clone()).unwrap(),
+            "step 7: pc 1 (acc +1) changed the accumulator from 5 to 6\n"
+        );
+        assert!(!debugger.command("quit", &mut out).unwrap());
+    }
+}
@@ -1,3 +1,11 @@
+pub mod bags;
+pub mod console;
+pub mod paragraphs;
+#[doc(hidden)]
+pub mod random;
+pub mod render;
+pub mod viewer;
+
 use std::ops::{Index, IndexMut};
 
 #[derive(Debug, Eq, PartialEq, Clone)]
@@ -0,0 +1,95 @@
+use std::io::{self, BufRead, Lines};
+
+/// A run of lines that are not blank, as in inputs where records are
+/// separated by empty lines
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Paragraph {
+    /// the 1-based line number of the first line
+    pub first_line: usize,
+    /// the lines without line endings and trailing whitespace
+    pub lines: Vec<String>,
+}
+
+impl Paragraph {
+    pub fn last_line(&self) -> usize {
+        self.first_line + self.lines.len() - 1
+    }
+
+    /// The whitespace separated words on all lines
+    pub fn words(&self) -> impl Iterator<Item = &str> {
+        self.lines.iter().flat_map(|line| line.split_whitespace())
+    }
+}
+
+/// Iterator over the paragraphs of a reader, see `paragraphs`
+pub struct Paragraphs<R> {
+    lines: Lines<R>,
+    line_number: usize,
+}
+
+/// Read the paragraphs one at a time. Lines may end in `\n` or `\r\n`, and
+/// any number of lines with only whitespace separate two paragraphs.
+pub fn paragraphs<R: BufRead>(reader: R) -> Paragraphs<R> {
+    Paragraphs {
+        lines: reader.lines(),
+        line_number: 0,
+    }
+}
+
+impl<R: BufRead> Iterator for Paragraphs<R> {
+    type Item = io::Result<Paragraph>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut paragraph: Option<Paragraph> = None;
+        for line in &mut self.lines {
+            let line = match line {
+                Ok(line) => line,
+                Err(error) => return Some(Err(error)),
+            };
+            self.line_number += 1;
+            let line_number = self.line_number;
+            let line = line.trim_end();
+            if line.is_empty() {
+                if paragraph.is_some() {
+                    break;
+                }
+            } else {
+                paragraph
+                    .get_or_insert_with(|| Paragraph {
+                        first_line: line_number,
+                        lines: Vec::new(),
+                    })
+                    .lines
+                    .push(line.to_string());
+            }
+        }
+        paragraph.map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators() {
+        let input = "\r\na b\r\nc\r\n  \r\n\r\n\td\ne  \n \n";
+        let found: Vec<Paragraph> = paragraphs(input.as_bytes()).map(Result::unwrap).collect();
+        assert_eq!(
+            found,
+            vec![
+                Paragraph {
+                    first_line: 2,
+                    lines: vec!["a b".to_string(), "c".to_string()],
+                },
+                Paragraph {
+                    first_line: 6,
+                    lines: vec!["\td".to_string(), "e".to_string()],
+                },
+            ]
+        );
+        assert_eq!(found[0].last_line(), 3);
+        assert_eq!(found[1].words().collect::<Vec<_>>(), vec!["d", "e"]);
+        assert_eq!(paragraphs("".as_bytes()).count(), 0);
+    }
+}
@@ -0,0 +1,113 @@
+use crate::Mat;
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use anyhow::Result;
+
+/// Write `mat` to a png file, drawing every element as a `scale` x `scale`
+/// block in the colour `colour` assigns to it.
+pub fn write_png<T: Clone>(
+    mat: &Mat<T>,
+    path: &Path,
+    scale: usize,
+    colour: impl Fn(&T) -> [u8; 3],
+) -> Result<()> {
+    let width = mat.width() * scale;
+    let height = mat.height() * scale;
+    let mut data = Vec::with_capacity(width * height * 3);
+    for y in 0..height {
+        for x in 0..width {
+            data.extend_from_slice(&colour(&mat[(x / scale, y / scale)]));
+        }
+    }
+
+    let mut encoder = png::Encoder::new(
+        BufWriter::new(File::create(path)?),
+        width as u32,
+        height as u32,
+    );
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&data)?;
+    Ok(())
+}
+
+/// Write every frame to a png file in `directory` as with `write_png`, named
+/// `prefix` followed by the frame number. The numbers are padded with zeros
+/// so the files sort in order.
+pub fn animate<T: Clone>(
+    frames: &[Mat<T>],
+    directory: &Path,
+    prefix: &str,
+    scale: usize,
+    colour: impl Fn(&T) -> [u8; 3],
+) -> Result<()> {
+    std::fs::create_dir_all(directory)?;
+    let digits = frames.len().saturating_sub(1).to_string().len();
+    for (number, frame) in frames.iter().enumerate() {
+        let path = directory.join(format!("{}{:0width$}.png", prefix, number, width = digits));
+        write_png(frame, &path, scale, &colour)?;
+    }
+    Ok(())
+}
+
+/// Lay out `tiles` in rows of `columns`, separated by a `gap` wide border of
+/// `background`. Tiles may differ in size, each cell is as large as the
+/// largest tile.
+pub fn contact_sheet<T: Clone>(
+    tiles: &[Mat<T>],
+    columns: usize,
+    gap: usize,
+    background: T,
+) -> Mat<T> {
+    let columns = columns.max(1);
+    let rows = tiles.len().div_ceil(columns);
+    let tile_width = tiles.iter().map(|tile| tile.width()).max().unwrap_or(0);
+    let tile_height = tiles.iter().map(|tile| tile.height()).max().unwrap_or(0);
+
+    let mut sheet = Mat::new(
+        columns * (tile_width + gap) + gap,
+        rows * (tile_height + gap) + gap,
+        background,
+    );
+    for (i, tile) in tiles.iter().enumerate() {
+        let left = gap + (i % columns) * (tile_width + gap);
+        let top = gap + (i / columns) * (tile_height + gap);
+        for x in 0..tile.width() {
+            for y in 0..tile.height() {
+                sheet[(left + x, top + y)] = tile[(x, y)].clone();
+            }
+        }
+    }
+    sheet
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contact_sheet_layout() {
+        let tiles = vec![Mat::new(2, 1, 1), Mat::new(1, 3, 2), Mat::new(2, 2, 3)];
+        let sheet = contact_sheet(&tiles, 2, 1, 0);
+        // two columns of cells 2 wide, two rows of cells 3 high, gaps of 1
+        assert_eq!((sheet.width(), sheet.height()), (7, 9));
+        let rows: Vec<String> = (0..sheet.height())
+            .map(|y| {
+                (0..sheet.width())
+                    .map(|x| sheet[(x, y)].to_string())
+                    .collect()
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            vec![
+                "0000000", //
+                "0110200", "0000200", "0000200", "0000000", "0330000", "0330000", "0000000",
+                "0000000",
+            ]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use crate::Mat;
+
+use std::collections::BTreeMap;
+use std::io::{stdout, Stdout, Write};
+
+use anyhow::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent};
+use crossterm::style::Print;
+use crossterm::terminal::{self, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, execute, queue};
+
+static HELP: &str = "n/p: next/previous  g/G: first/last  arrows/PgUp/PgDn: pan  q: quit";
+
+/// Restores the terminal when dropped, so it is also restored on errors
+struct RawScreen {
+    out: Stdout,
+}
+
+impl RawScreen {
+    fn enter() -> Result<RawScreen> {
+        let mut out = stdout();
+        terminal::enable_raw_mode()?;
+        execute!(out, EnterAlternateScreen, cursor::Hide)?;
+        Ok(RawScreen { out })
+    }
+}
+
+impl Drop for RawScreen {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+struct Viewer<'a, T: Clone, F> {
+    history: &'a [Mat<T>],
+    glyph: F,
+    generation: usize,
+    left: usize,
+    top: usize,
+}
+
+impl<'a, T: Clone, F: Fn(&T) -> char> Viewer<'a, T, F> {
+    fn current(&self) -> &Mat<T> {
+        &self.history[self.generation]
+    }
+
+    fn counts(&self) -> String {
+        let mut counts = BTreeMap::new();
+        for tile in self.current().iter_elements() {
+            *counts.entry((self.glyph)(tile)).or_insert(0) += 1;
+        }
+        counts
+            .iter()
+            .map(|(glyph, count)| format!("'{}': {}", glyph, count))
+            .collect::<Vec<_>>()
+            .join("  ")
+    }
+
+    fn draw(&self, out: &mut Stdout) -> Result<()> {
+        let (columns, rows) = terminal::size()?;
+        let (columns, rows) = (columns as usize, rows as usize);
+        let map = self.current();
+        queue!(out, terminal::Clear(ClearType::All), cursor::MoveTo(0, 0))?;
+        let status = format!(
+            "generation {}/{}  offset ({}, {})  {}",
+            self.generation,
+            self.history.len() - 1,
+            self.left,
+            self.top,
+            self.counts()
+        );
+        queue!(out, Print(truncate(&status, columns)))?;
+
+        for row in 0..rows.saturating_sub(2) {
+            let y = self.top + row;
+            if y >= map.height() {
+                break;
+            }
+            let line: String = (self.left..map.width().min(self.left + columns))
+                .map(|x| (self.glyph)(&map[(x, y)]))
+                .collect();
+            queue!(out, cursor::MoveTo(0, row as u16 + 1), Print(line))?;
+        }
+
+        queue!(
+            out,
+            cursor::MoveTo(0, rows.saturating_sub(1) as u16),
+            Print(truncate(HELP, columns))
+        )?;
+        out.flush()?;
+        Ok(())
+    }
+
+    fn pan(&mut self, horizontal: isize, vertical: isize) {
+        let map = self.current();
+        let max_left = map.width().saturating_sub(1) as isize;
+        let max_top = map.height().saturating_sub(1) as isize;
+        self.left = (self.left as isize + horizontal).max(0).min(max_left) as usize;
+        self.top = (self.top as isize + vertical).max(0).min(max_top) as usize;
+    }
+
+    /// Handle a key press, `page` rows at a time for page up and down.
+    /// Returns false when the viewer should close.
+    fn handle_key(&mut self, key: KeyEvent, page: isize) -> bool {
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Char('n') | KeyCode::Char(' ') => {
+                self.generation = (self.generation + 1).min(self.history.len() - 1)
+            }
+            KeyCode::Char('p') | KeyCode::Backspace => {
+                self.generation = self.generation.saturating_sub(1)
+            }
+            KeyCode::Char('g') | KeyCode::Home => self.generation = 0,
+            KeyCode::Char('G') | KeyCode::End => self.generation = self.history.len() - 1,
+            KeyCode::Left => self.pan(-1, 0),
+            KeyCode::Right => self.pan(1, 0),
+            KeyCode::Up => self.pan(0, -1),
+            KeyCode::Down => self.pan(0, 1),
+            KeyCode::PageUp => self.pan(0, -page),
+            KeyCode::PageDown => self.pan(0, page),
+            _ => {}
+        }
+        true
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Interactively step through the generations of a grid simulation in the
+/// terminal, drawing every tile as the character `glyph` returns for it.
+pub fn view<T: Clone>(history: &[Mat<T>], glyph: impl Fn(&T) -> char) -> Result<()> {
+    if history.is_empty() {
+        return Ok(());
+    }
+    let mut viewer = Viewer {
+        history,
+        glyph,
+        generation: 0,
+        left: 0,
+        top: 0,
+    };
+
+    let mut screen = RawScreen::enter()?;
+    loop {
+        viewer.draw(&mut screen.out)?;
+        match event::read()? {
+            Event::Key(key) => {
+                let page = terminal::size()?.1.saturating_sub(2).max(1) as isize;
+                if !viewer.handle_key(key, page) {
+                    return Ok(());
+                }
+            }
+            Event::Resize(_, _) | Event::Mouse(_) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn viewer(history: &[Mat<u8>]) -> Viewer<'_, u8, impl Fn(&u8) -> char> {
+        Viewer {
+            history,
+            glyph: |&tile: &u8| if tile == 1 { '#' } else { '.' },
+            generation: 0,
+            left: 0,
+            top: 0,
+        }
+    }
+
+    #[test]
+    fn stepping_through_generations() {
+        let mut last = Mat::new(3, 2, 0);
+        last[(1, 1)] = 1;
+        let history = vec![Mat::new(3, 2, 0), Mat::new(3, 2, 1), last];
+        let mut viewer = viewer(&history);
+        assert_eq!(viewer.counts(), "'.': 6");
+
+        assert!(viewer.handle_key(KeyCode::Char('p').into(), 1));
+        assert_eq!(viewer.generation, 0);
+        assert!(viewer.handle_key(KeyCode::Char('n').into(), 1));
+        assert_eq!(viewer.counts(), "'#': 6");
+        assert!(viewer.handle_key(KeyCode::End.into(), 1));
+        assert_eq!(viewer.generation, 2);
+        assert_eq!(viewer.counts(), "'#': 1  '.': 5");
+        assert!(viewer.handle_key(KeyCode::Char('n').into(), 1));
+        assert_eq!(viewer.generation, 2);
+        assert!(viewer.handle_key(KeyCode::Char('g').into(), 1));
+        assert_eq!(viewer.generation, 0);
+        assert!(!viewer.handle_key(KeyCode::Char('q').into(), 1));
+    }
+
+    #[test]
+    fn panning_stays_on_the_map() {
+        let history = vec![Mat::new(3, 5, 0)];
+        let mut viewer = viewer(&history);
+        assert!(viewer.handle_key(KeyCode::Left.into(), 2));
+        assert!(viewer.handle_key(KeyCode::Up.into(), 2));
+        assert_eq!((viewer.left, viewer.top), (0, 0));
+
+        assert!(viewer.handle_key(KeyCode::PageDown.into(), 2));
+        assert_eq!((viewer.left, viewer.top), (0, 2));
+        assert!(viewer.handle_key(KeyCode::PageDown.into(), 2));
+        assert!(viewer.handle_key(KeyCode::PageDown.into(), 2));
+        assert_eq!(viewer.top, 4);
+
+        viewer.pan(10, -1);
+        assert_eq!((viewer.left, viewer.top), (2, 3));
+        assert!(viewer.handle_key(KeyCode::PageUp.into(), 2));
+        assert_eq!((viewer.left, viewer.top), (2, 1));
+    }
+}